@@ -0,0 +1,394 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+use crate::editor::paged_file::PagedFile;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Source {
+    Original,
+    Added,
+}
+
+// A run of bytes taken from one of the two sources
+#[derive(Clone, Copy)]
+struct Piece {
+    source: Source,
+    // Position in the source
+    start: usize,
+    length: usize,
+    // Position in the edited buffer
+    offset: usize,
+}
+
+/// Piece table over a file.
+///
+/// The original file is only read page by page when its bytes are needed, and is
+/// never modified: inserted bytes go to an append-only buffer, and the content is
+/// described by a list of pieces pointing into one or the other. Edits stay in
+/// memory until the buffer is written to a file.
+///
+/// If the file can't be read anymore, the missing bytes are read as zeros and
+/// the error is kept until `take_error` is called.
+pub struct Buffer {
+    original: PagedFile,
+    added: Vec<u8>,
+    pieces: Vec<Piece>,
+    length: usize,
+    error: RefCell<Option<io::Error>>,
+}
+
+impl Buffer {
+    pub fn open(file_name: &str) -> io::Result<Buffer> {
+        let original = PagedFile::open(file_name)?;
+        let length = original.len();
+        let mut pieces = Vec::new();
+        if length > 0 {
+            pieces.push(Piece {
+                source: Source::Original,
+                start: 0,
+                length,
+                offset: 0,
+            });
+        }
+        Ok(Buffer {
+            original,
+            added: Vec::new(),
+            pieces,
+            length,
+            error: RefCell::new(None),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn get(&self, index: usize) -> u8 {
+        self.slice(index, index + 1)[0]
+    }
+
+    /// Copy of the bytes in `start..end`.
+    pub fn slice(&self, start: usize, end: usize) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(end.saturating_sub(start));
+        if start >= end {
+            return bytes;
+        }
+        for piece in &self.pieces[self.locate(start)..] {
+            if piece.offset >= end {
+                break;
+            }
+            let from = piece.start + start.saturating_sub(piece.offset);
+            let to = piece.start + (end - piece.offset).min(piece.length);
+            match piece.source {
+                Source::Original => {
+                    let read_start = bytes.len();
+                    if let Err(e) = self.original.read(from, to, &mut bytes) {
+                        bytes.resize(read_start + to - from, 0);
+                        *self.error.borrow_mut() = Some(e);
+                    }
+                }
+                Source::Added => bytes.extend_from_slice(&self.added[from..to]),
+            }
+        }
+        bytes
+    }
+
+    /// The bytes in `offset..offset + length`, as the pieces holding them,
+    /// without reading them.
+    pub fn span(&self, offset: usize, length: usize) -> Span {
+        let mut span = Span::default();
+        let end = offset + length;
+        if offset >= end {
+            return span;
+        }
+        for piece in &self.pieces[self.locate(offset)..] {
+            if piece.offset >= end {
+                break;
+            }
+            let from = offset.saturating_sub(piece.offset);
+            let to = (end - piece.offset).min(piece.length);
+            span.push(Piece {
+                start: piece.start + from,
+                length: to - from,
+                ..*piece
+            });
+        }
+        span
+    }
+
+    /// Replace `length` bytes at `offset` by `bytes`, and return the removed bytes.
+    pub fn replace(&mut self, offset: usize, length: usize, bytes: &[u8]) -> Span {
+        let mut span = Span::default();
+        span.push(Piece {
+            source: Source::Added,
+            start: self.added.len(),
+            length: bytes.len(),
+            offset: 0,
+        });
+        self.added.extend_from_slice(bytes);
+        self.replace_span(offset, length, &span)
+    }
+
+    /// Replace `length` bytes at `offset` by the bytes of `span`, taken from
+    /// this buffer, and return the removed bytes.
+    pub fn replace_span(&mut self, offset: usize, length: usize, span: &Span) -> Span {
+        let first = self.split(offset);
+        let last = self.split(offset + length);
+        let mut removed = Span::default();
+        for piece in self.pieces.splice(first..last, span.pieces.iter().copied()) {
+            removed.push(piece);
+        }
+
+        // Typing usually appends right after the previous insertion, and
+        // undoing a deletion puts back the end of a cut piece: in both cases
+        // the pieces are joined instead of adding one more
+        self.join(first + span.pieces.len());
+        self.join(first);
+
+        // Update the positions of the following pieces
+        let mut position = if first > 0 {
+            self.pieces[first - 1].offset
+        } else {
+            0
+        };
+        for piece in self.pieces[first.saturating_sub(1)..].iter_mut() {
+            piece.offset = position;
+            position += piece.length;
+        }
+        self.length = position;
+
+        removed
+    }
+
+    pub fn write_to(&self, writer: &mut impl Write) -> io::Result<()> {
+        for piece in self.pieces.iter() {
+            let end = piece.start + piece.length;
+            match piece.source {
+                Source::Original => self.original.copy_to(piece.start, end, writer)?,
+                Source::Added => writer.write_all(&self.added[piece.start..end])?,
+            }
+        }
+        Ok(())
+    }
+
+    pub fn take_error(&self) -> Option<io::Error> {
+        self.error.borrow_mut().take()
+    }
+
+    // Index of the piece containing `offset`
+    fn locate(&self, offset: usize) -> usize {
+        self.pieces.partition_point(|piece| piece.offset + piece.length <= offset)
+    }
+
+    // Cut the piece containing `offset` in two, so that a piece starts at `offset`.
+    // Return the index of that piece.
+    fn split(&mut self, offset: usize) -> usize {
+        let index = self.locate(offset);
+        if index == self.pieces.len() || self.pieces[index].offset == offset {
+            return index;
+        }
+        let piece = self.pieces[index];
+        let head = offset - piece.offset;
+        self.pieces[index].length = head;
+        self.pieces.insert(index + 1, Piece {
+            source: piece.source,
+            start: piece.start + head,
+            length: piece.length - head,
+            offset,
+        });
+        index + 1
+    }
+
+    // Join the piece at `index` to the previous one, when it continues it
+    fn join(&mut self, index: usize) {
+        if index == 0 || index >= self.pieces.len() {
+            return;
+        }
+        let piece = self.pieces[index];
+        let previous = &mut self.pieces[index - 1];
+        if previous.source == piece.source && previous.start + previous.length == piece.start {
+            previous.length += piece.length;
+            self.pieces.remove(index);
+        }
+    }
+}
+
+/// Bytes of a buffer kept as the pieces holding them instead of a copy, so
+/// that keeping the bytes of a change doesn't read them, whatever its size.
+#[derive(Clone, Default)]
+pub struct Span {
+    // Positions relative to the start of the span
+    pieces: Vec<Piece>,
+    length: usize,
+}
+
+impl Span {
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// The bytes in `start..end` of the span, empty if `start >= end`.
+    pub fn slice(&self, start: usize, end: usize) -> Span {
+        let mut span = Span::default();
+        let end = end.min(self.length);
+        if start >= end {
+            return span;
+        }
+        let first = self.pieces.partition_point(|piece| piece.offset + piece.length <= start);
+        for piece in &self.pieces[first..] {
+            if piece.offset >= end {
+                break;
+            }
+            let from = start.saturating_sub(piece.offset);
+            let to = (end - piece.offset).min(piece.length);
+            span.push(Piece {
+                start: piece.start + from,
+                length: to - from,
+                ..*piece
+            });
+        }
+        span
+    }
+
+    /// Add the bytes of `other` after these ones.
+    pub fn append(&mut self, other: &Span) {
+        for piece in &other.pieces {
+            self.push(*piece);
+        }
+    }
+
+    fn push(&mut self, piece: Piece) {
+        if piece.length == 0 {
+            return;
+        }
+        match self.pieces.last_mut() {
+            Some(last) if last.source == piece.source && last.start + last.length == piece.start => {
+                last.length += piece.length;
+            }
+            _ => self.pieces.push(Piece {
+                offset: self.length,
+                ..piece
+            }),
+        }
+        self.length += piece.length;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::env;
+    use std::fs;
+    use std::process;
+
+    use super::*;
+
+    /// A buffer over a new file holding `bytes`, removed once opened.
+    pub fn buffer_with(name: &str, bytes: &[u8]) -> Buffer {
+        let path = env::temp_dir().join(format!("rhexed-test-{}-{}", process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        let buffer = Buffer::open(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+        buffer
+    }
+
+    fn content(buffer: &Buffer) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        buffer.write_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    // Same sequence of numbers in every run
+    fn random(seed: &mut u64) -> usize {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (*seed >> 33) as usize
+    }
+
+    #[test]
+    fn replace_as_a_vec() {
+        let original: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let mut buffer = buffer_with("replace", &original);
+        let mut expected = original.clone();
+        let mut seed = 1;
+        for step in 0..500 {
+            let offset = random(&mut seed) % (expected.len() + 1);
+            let length = random(&mut seed) % (expected.len() - offset + 1).min(20);
+            let bytes: Vec<u8> = (0..random(&mut seed) % 8).map(|_| random(&mut seed) as u8).collect();
+
+            let removed = buffer.replace(offset, length, &bytes);
+            let removed_bytes: Vec<u8> = expected.splice(offset..offset + length, bytes).collect();
+            assert_eq!(removed.len(), removed_bytes.len(), "step {}", step);
+            assert_eq!(buffer.len(), expected.len(), "step {}", step);
+
+            let start = random(&mut seed) % (expected.len() + 1);
+            let end = start + random(&mut seed) % (expected.len() - start + 1);
+            assert_eq!(buffer.slice(start, end), &expected[start..end], "step {}", step);
+        }
+        assert_eq!(content(&buffer), expected);
+    }
+
+    #[test]
+    fn replace_span_puts_back_the_removed_bytes() {
+        let original: Vec<u8> = (0..100).map(|i| i as u8).collect();
+        let mut buffer = buffer_with("spans", &original);
+        let mut seed = 2;
+        let mut changes = Vec::new();
+        for _ in 0..100 {
+            let offset = random(&mut seed) % (buffer.len() + 1);
+            let length = random(&mut seed) % (buffer.len() - offset + 1).min(30);
+            let bytes = vec![random(&mut seed) as u8; random(&mut seed) % 10];
+            let before = content(&buffer);
+            let old = buffer.replace(offset, length, &bytes);
+            let new = buffer.span(offset, bytes.len());
+            changes.push((offset, old, new, before));
+        }
+        let after = content(&buffer);
+
+        for (offset, old, new, before) in changes.iter().rev() {
+            buffer.replace_span(*offset, new.len(), old);
+            assert_eq!(&content(&buffer), before);
+        }
+        assert_eq!(content(&buffer), original);
+        // Once everything is undone, the pieces are joined back into the file
+        assert_eq!(buffer.pieces.len(), 1);
+
+        for (offset, old, new, _) in changes.iter() {
+            buffer.replace_span(*offset, old.len(), new);
+        }
+        assert_eq!(content(&buffer), after);
+    }
+
+    #[test]
+    fn typing_extends_a_single_piece() {
+        let mut buffer = buffer_with("typing", b"0123456789");
+        for (k, byte) in b"abcdef".iter().enumerate() {
+            buffer.replace(4 + k, 0, &[*byte]);
+        }
+        assert_eq!(content(&buffer), b"0123abcdef456789");
+        assert_eq!(buffer.pieces.len(), 3);
+    }
+
+    #[test]
+    fn span_slice_and_append() {
+        let mut buffer = buffer_with("slice", b"0123456789");
+        buffer.replace(5, 0, b"abc");
+        let span = buffer.span(2, 8);
+        assert_eq!(span.len(), 8);
+
+        let mut joined = span.slice(0, 3);
+        joined.append(&span.slice(3, 100));
+        assert_eq!(joined.len(), 8);
+        assert!(span.slice(5, 5).is_empty());
+        assert!(span.slice(6, 2).is_empty());
+
+        buffer.replace_span(0, 0, &joined);
+        assert_eq!(content(&buffer), b"234abc5601234abc56789");
+    }
+}
@@ -0,0 +1,205 @@
+// CP437 characters of the bytes 0x80 to 0xff
+const CP437_HIGH: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅ",
+    "ÉæÆôöòûùÿÖÜ¢£¥₧ƒ",
+    "áíóúñÑªº¿⌐¬½¼¡«»",
+    "░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧",
+    "╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀",
+    "αßΓπΣσµτΦΘΩδ∞φε∩",
+    "≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}",
+);
+
+// EBCDIC (CP037) characters of the bytes 0x40 to 0xff, the lower ones
+// being control codes
+const EBCDIC_HIGH: &str = concat!(
+    " \u{a0}âäàáãåçñ¢.<(+|",
+    "&éêëèíîïìß!$*);¬",
+    "-/ÂÄÀÁÃÅÇÑ¦,%_>?",
+    "øÉÊËÈÍÎÏÌ`:#@'=\"",
+    "Øabcdefghi«»ðýþ±",
+    "°jklmnopqrªºæ¸Æ¤",
+    "µ~stuvwxyz¡¿ÐÝÞ®",
+    "^£¥·©§¶¼½¾[]¯¨´×",
+    "{ABCDEFGHI\u{ad}ôöòóõ",
+    "}JKLMNOPQR¹ûüùúÿ",
+    "\\÷STUVWXYZ²ÔÖÒÓÕ",
+    "0123456789³ÛÜÙÚ\u{9f}",
+);
+
+/// How a byte of the text side bar is shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Glyph {
+    Char(char),
+    // A byte of a character shown on a previous byte
+    Continuation,
+    NotPrintable,
+}
+
+/// The character encoding of the text side bar, also used to write the
+/// characters typed in ascii insert mode.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Charset {
+    Ascii,
+    Latin1,
+    Cp437,
+    Ebcdic,
+    Utf8,
+    Utf16Le,
+}
+
+impl Charset {
+    pub fn next(&self) -> Charset {
+        match self {
+            Charset::Ascii => Charset::Latin1,
+            Charset::Latin1 => Charset::Cp437,
+            Charset::Cp437 => Charset::Ebcdic,
+            Charset::Ebcdic => Charset::Utf8,
+            Charset::Utf8 => Charset::Utf16Le,
+            Charset::Utf16Le => Charset::Ascii,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Charset::Ascii => "ascii",
+            Charset::Latin1 => "latin-1",
+            Charset::Cp437 => "cp437",
+            Charset::Ebcdic => "ebcdic (cp037)",
+            Charset::Utf8 => "utf-8",
+            Charset::Utf16Le => "utf-16le",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Charset> {
+        let mut charset = Charset::Ascii;
+        loop {
+            if charset.name() == name || charset.name().split(' ').next() == Some(name) {
+                return Some(charset);
+            }
+            charset = charset.next();
+            if charset == Charset::Ascii {
+                return None;
+            }
+        }
+    }
+
+    /// Glyphs of `bytes`, which are at `offset` in the file.
+    ///
+    /// The multibyte characters are shown on their first byte, so a few
+    /// bytes before the ones to show should be given to find where the
+    /// characters start.
+    pub fn glyphs(&self, bytes: &[u8], offset: usize) -> Vec<Glyph> {
+        match self {
+            Charset::Utf8 => utf8_glyphs(bytes),
+            Charset::Utf16Le => utf16_glyphs(bytes, offset),
+            _ => bytes.iter().map(|byte| glyph(self.decode(*byte))).collect(),
+        }
+    }
+
+    // Character of a byte, for the single byte charsets
+    fn decode(&self, byte: u8) -> char {
+        match self {
+            Charset::Ascii if byte < 0x80 => byte as char,
+            Charset::Latin1 => byte as char,
+            Charset::Cp437 if byte >= 0x80 => CP437_HIGH.chars().nth(byte as usize - 0x80).unwrap_or('\0'),
+            Charset::Cp437 => byte as char,
+            Charset::Ebcdic if byte >= 0x40 => EBCDIC_HIGH.chars().nth(byte as usize - 0x40).unwrap_or('\0'),
+            _ => '\0',
+        }
+    }
+
+    /// Bytes of a typed character.
+    pub fn encode(&self, c: char) -> Result<Vec<u8>, String> {
+        let bytes = match self {
+            Charset::Ascii if c.is_ascii() => Some(vec![c as u8]),
+            Charset::Latin1 if (c as u32) < 0x100 => Some(vec![c as u8]),
+            Charset::Cp437 | Charset::Ebcdic => (0..=255u8)
+                .find(|byte| glyph(self.decode(*byte)) == Glyph::Char(c))
+                .map(|byte| vec![byte]),
+            Charset::Utf8 => Some(c.to_string().into_bytes()),
+            Charset::Utf16Le => Some(c.encode_utf16(&mut [0; 2]).iter().flat_map(|unit| unit.to_le_bytes()).collect()),
+            _ => None,
+        };
+        bytes.ok_or(format!("'{}' can't be written in {}", c, self.name()))
+    }
+}
+
+fn glyph(c: char) -> Glyph {
+    if c.is_control() || is_zero_width(c) {
+        Glyph::NotPrintable
+    } else {
+        Glyph::Char(c)
+    }
+}
+
+fn utf8_glyphs(bytes: &[u8]) -> Vec<Glyph> {
+    let mut glyphs = Vec::with_capacity(bytes.len());
+    while glyphs.len() < bytes.len() {
+        let rest = &bytes[glyphs.len()..];
+        let length = match rest[0] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        match rest.get(..length).and_then(|sequence| std::str::from_utf8(sequence).ok()) {
+            Some(text) => {
+                glyphs.push(text.chars().next().map_or(Glyph::NotPrintable, glyph));
+                glyphs.extend((1..length).map(|_| Glyph::Continuation));
+            }
+            None => glyphs.push(Glyph::NotPrintable),
+        }
+    }
+    glyphs
+}
+
+// The characters start at even offsets in the file
+fn utf16_glyphs(bytes: &[u8], offset: usize) -> Vec<Glyph> {
+    let mut glyphs = Vec::with_capacity(bytes.len());
+    if offset % 2 == 1 && !bytes.is_empty() {
+        glyphs.push(Glyph::Continuation);
+    }
+    while glyphs.len() < bytes.len() {
+        let units: Vec<u16> = bytes[glyphs.len()..]
+            .chunks_exact(2)
+            .take(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        match char::decode_utf16(units).next() {
+            Some(Ok(c)) => {
+                glyphs.push(glyph(c));
+                glyphs.extend((1..c.len_utf16() * 2).map(|_| Glyph::Continuation));
+            }
+            Some(Err(_)) => glyphs.extend([Glyph::NotPrintable, Glyph::Continuation]),
+            None => glyphs.push(Glyph::NotPrintable),
+        }
+    }
+    glyphs.truncate(bytes.len());
+    glyphs
+}
+
+fn is_zero_width(c: char) -> bool {
+    matches!(c as u32, 0x300..=0x36f | 0x200b..=0x200f | 0xad | 0xfe00..=0xfe0f | 0xfeff)
+}
+
+/// Whether a character takes two columns of the terminal.
+pub fn is_wide(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x115f
+            | 0x2e80..=0x303e
+            | 0x3041..=0x33ff
+            | 0x3400..=0x4dbf
+            | 0x4e00..=0x9fff
+            | 0xa000..=0xa4cf
+            | 0xac00..=0xd7a3
+            | 0xf900..=0xfaff
+            | 0xfe30..=0xfe4f
+            | 0xff00..=0xff60
+            | 0xffe0..=0xffe6
+            | 0x1f300..=0x1f64f
+            | 0x1f900..=0x1f9ff
+            | 0x20000..=0x3fffd
+    )
+}
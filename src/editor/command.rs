@@ -0,0 +1,90 @@
+use crate::editor::replace_mode::parse_term;
+
+// The names which can be completed, the aliases being only typed
+pub const COMMANDS: [&str; 11] = [
+    "write", "wq", "quit", "reload", "goto", "set", "fill", "registers", "undo", "redo", "help",
+];
+pub const SET_OPTIONS: [&str; 13] = [
+    "width=", "group=", "gap", "nogap", "radix=", "encoding=", "inspector", "noinspector", "pointer=",
+    "base=", "backup", "nobackup", "all",
+];
+
+/// A command typed on the command line, after ':'.
+pub enum Command {
+    // Write to the file, or to a copy of it
+    Write(Option<String>),
+    WriteQuit,
+    Quit { force: bool },
+    Reload,
+    // An address, evaluated by the editor
+    Goto(String),
+    Set(Vec<String>),
+    Fill(Vec<u8>),
+    Registers,
+    Undo,
+    Redo,
+    Help,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (name, arguments) = match line.split_once(char::is_whitespace) {
+            Some((name, arguments)) => (name, arguments.trim()),
+            None => (line, ""),
+        };
+        let no_arguments = |command: Command| {
+            if arguments.is_empty() {
+                Ok(command)
+            } else {
+                Err(format!("Unexpected argument for {}: {}", name, arguments))
+            }
+        };
+
+        match name {
+            "" => Err("No command".to_string()),
+            "w" | "write" if arguments.is_empty() => Ok(Command::Write(None)),
+            "w" | "write" => Ok(Command::Write(Some(arguments.to_string()))),
+            "wq" | "x" => no_arguments(Command::WriteQuit),
+            "q" | "quit" => no_arguments(Command::Quit { force: false }),
+            "q!" | "quit!" => no_arguments(Command::Quit { force: true }),
+            "e" | "reload" => no_arguments(Command::Reload),
+            "goto" | "g" if arguments.is_empty() => Err("Expected: goto ADDRESS".to_string()),
+            "goto" | "g" => Ok(Command::Goto(arguments.to_string())),
+            "set" => Ok(Command::Set(arguments.split_whitespace().map(str::to_string).collect())),
+            "fill" => {
+                let pattern = parse_term(&mut arguments.chars().peekable())?;
+                if pattern.is_empty() || pattern.has_wildcards() {
+                    return Err("Expected: fill BYTES (hex or \"text\")".to_string());
+                }
+                Ok(Command::Fill(pattern.bytes().to_vec()))
+            }
+            "reg" | "registers" => no_arguments(Command::Registers),
+            "u" | "undo" => no_arguments(Command::Undo),
+            "redo" => no_arguments(Command::Redo),
+            "h" | "help" => no_arguments(Command::Help),
+            // A bare address, as in vim
+            _ if name.starts_with(|c: char| c.is_ascii_digit() || "+-('".contains(c)) => {
+                Ok(Command::Goto(line.to_string()))
+            }
+            _ => Err(format!("Unknown command '{}'", name)),
+        }
+    }
+}
+
+/// Names which can complete the last word of `line`.
+pub fn completions(line: &str) -> Vec<String> {
+    let words: Vec<&str> = line.split(' ').collect();
+    let last = words[words.len() - 1];
+    let names: &[&str] = match words.as_slice() {
+        [_] => &COMMANDS,
+        ["set", ..] => &SET_OPTIONS,
+        _ => &[],
+    };
+    let start = &line[..line.len() - last.len()];
+    names
+        .iter()
+        .filter(|name| name.starts_with(last))
+        .map(|name| format!("{}{}", start, name))
+        .collect()
+}
@@ -0,0 +1,215 @@
+use crossterm::event::KeyCode;
+use crate::editor::{Editor, Mode, EditorError};
+use crate::editor::command::{self, Command};
+use crate::editor::charset::Charset;
+use crate::editor::layout::LineWidth;
+use crate::editor::expression;
+use crate::editor::marks;
+use crate::editor::pointer::parse_pointer_format;
+use crate::editor::radix::Radix;
+use crate::editor::save::save;
+
+impl Editor {
+    pub fn command_inputs(&mut self, key_code: KeyCode) {
+        if key_code != KeyCode::Tab {
+            self.completions.clear();
+        }
+        match key_code {
+            KeyCode::Esc => {
+                self.command_range = None;
+                self.mode = Mode::Normal;
+                self.refresh = true;
+            }
+            KeyCode::Backspace => {
+                // Erasing the ':' leaves the command line, as in vim
+                if self.input.pop().is_none() {
+                    self.command_range = None;
+                    self.mode = Mode::Normal;
+                }
+                self.refresh = true;
+            }
+            KeyCode::Enter => {
+                let line = self.input.clone();
+                if !line.trim().is_empty() && self.command_history.last() != Some(&line) {
+                    self.command_history.push(line.clone());
+                }
+                self.command_history_index = self.command_history.len();
+                self.mode = Mode::Normal;
+                self.run_command(&line);
+                self.command_range = None;
+                self.refresh = true;
+            }
+            KeyCode::Up if self.command_history_index > 0 => {
+                self.command_history_index -= 1;
+                self.input = self.command_history[self.command_history_index].clone();
+                self.refresh = true;
+            }
+            KeyCode::Down if self.command_history_index < self.command_history.len() => {
+                self.command_history_index += 1;
+                self.input = self.command_history.get(self.command_history_index).cloned().unwrap_or_default();
+                self.refresh = true;
+            }
+            KeyCode::Tab => {
+                // Each Tab gives the next name starting with what was typed,
+                // and then what was typed again
+                if self.completions.is_empty() {
+                    self.completions = command::completions(&self.input);
+                    if self.completions.len() > 1 {
+                        self.completions.push(self.input.clone());
+                    }
+                    self.completion_index = 0;
+                } else {
+                    self.completion_index = (self.completion_index + 1) % self.completions.len();
+                }
+                if let Some(line) = self.completions.get(self.completion_index) {
+                    self.input = line.clone();
+                    self.refresh = true;
+                }
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.refresh = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Run a command line, as typed after ':'.
+    pub fn run_command(&mut self, line: &str) {
+        let command = match Command::parse(line) {
+            Ok(command) => command,
+            Err(e) => {
+                self.show_error(e);
+                return;
+            }
+        };
+        match command {
+            Command::Write(None) => self.write(),
+            Command::Write(Some(file_name)) => match save(&self.buffer, &file_name, self.backup) {
+                Ok(()) => self.show_info(format!("Written to {}", file_name)),
+                Err(e) => self.show_error(EditorError::Write(file_name, e).to_string()),
+            },
+            Command::WriteQuit => {
+                self.write();
+                self.exit = !self.is_modified();
+            }
+            Command::Quit { force } => {
+                self.exit = true;
+                self.force_exit = force;
+            }
+            Command::Reload => self.reload(),
+            Command::Goto(address) => match self.evaluate_address(&address) {
+                Ok(address) => self.jump_to(address),
+                Err(e) => self.show_error(e),
+            },
+            Command::Set(options) if options.is_empty() => self.show_settings(),
+            Command::Set(options) => {
+                for option in options {
+                    if let Err(e) = self.set_option(&option) {
+                        self.show_error(e);
+                        break;
+                    }
+                }
+            }
+            Command::Fill(bytes) => match self.command_range {
+                Some((start, end)) => {
+                    let filled: Vec<u8> = bytes.iter().copied().cycle().take(end - start).collect();
+                    self.replace_bytes(start, filled.len(), &filled, false);
+                }
+                None => self.show_error("Nothing to fill, select the bytes first (v, then f)".to_string()),
+            },
+            Command::Registers => self.show_registers(),
+            Command::Undo => {
+                self.undo();
+            }
+            Command::Redo => {
+                self.redo();
+            }
+            Command::Help => self.mode = Mode::Help,
+        }
+        self.refresh = true;
+    }
+
+    /// Offset in the file of an address expression, typed in a command or
+    /// in jump mode. An address starting with `+` or `-` is relative to the
+    /// cursor.
+    pub fn evaluate_address(&self, text: &str) -> Result<usize, String> {
+        let mut address = expression::evaluate(text, &|name| self.address_name(name))?;
+        if text.trim_start().starts_with(['+', '-']) {
+            address += self.cursor_index as i128;
+        }
+        if address < 0 || address >= self.buffer.len() as i128 {
+            let sign = if address < 0 { "-" } else { "" };
+            return Err(format!("Address {} ({}{:#x}) is out of the file", text.trim(), sign, address.unsigned_abs()));
+        }
+        Ok(address as usize)
+    }
+
+    // Value of a name in an address expression
+    fn address_name(&self, name: &str) -> Result<i128, String> {
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            _ if name == "cur" => Ok(self.cursor_index as i128),
+            _ if name == "end" => Ok(self.buffer.len() as i128),
+            (Some(mark), None) if marks::is_mark_name(mark) => {
+                self.marks.get(mark).map(|offset| offset as i128).ok_or(format!("Mark {} is not set", mark))
+            }
+            _ => Err(format!("Unknown name '{}' (cur, end or a mark)", name)),
+        }
+    }
+
+    // Change a setting: `name=value`, `name` or `noname`
+    fn set_option(&mut self, option: &str) -> Result<(), String> {
+        let (name, value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (option, None),
+        };
+        let invalid = || format!("Invalid value for {}: {}", name, value.unwrap_or(""));
+        match (name, value) {
+            ("width", Some("auto")) => self.layout.width = LineWidth::Auto,
+            ("width", Some(bytes)) => match bytes.parse::<usize>() {
+                Ok(bytes) if [8, 16, 24, 32].contains(&bytes) => self.layout.width = LineWidth::Bytes(bytes),
+                _ => return Err(invalid()),
+            },
+            ("group", Some(bytes)) => match bytes.parse::<usize>() {
+                Ok(bytes) if [1, 2, 4, 8].contains(&bytes) => self.layout.group = bytes,
+                _ => return Err(invalid()),
+            },
+            ("gap", None) => self.layout.gap = true,
+            ("nogap", None) => self.layout.gap = false,
+            ("radix", Some(name)) => {
+                self.layout.radix = Radix::from_name(name).ok_or_else(invalid)?;
+                self.nibble_index = 0;
+            }
+            ("encoding", Some(name)) => self.charset = Charset::from_name(name).ok_or_else(invalid)?,
+            ("pointer", Some(name)) => {
+                let (pointer_type, endianness) = parse_pointer_format(name).ok_or_else(invalid)?;
+                self.pointer_type = pointer_type;
+                self.pointer_endianness = endianness.unwrap_or(self.pointer_endianness);
+            }
+            ("base", Some(text)) => {
+                self.pointer_base = expression::evaluate(text, &|name| self.address_name(name))?;
+            }
+            ("inspector", None) => self.show_inspector = true,
+            ("noinspector", None) => self.show_inspector = false,
+            ("backup", None) => self.backup = true,
+            ("nobackup", None) => self.backup = false,
+            ("all", None) => self.show_settings(),
+            _ => return Err(format!("Unknown option '{}'", option)),
+        }
+        self.refresh = true;
+        Ok(())
+    }
+
+    fn show_settings(&mut self) {
+        let backup = if self.backup { "backup" } else { "nobackup" };
+        self.show_info(format!(
+            "{}, {}, {} text, {}, {}",
+            self.layout.name(),
+            self.layout.radix.name(),
+            self.charset.name(),
+            self.pointer_name(),
+            backup
+        ));
+    }
+}
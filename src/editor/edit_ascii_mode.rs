@@ -1,3 +1,4 @@
+use std::cmp;
 
 use crate::editor::{Editor, Mode};
 use crossterm::event::KeyCode;
@@ -5,42 +6,47 @@ use crossterm::event::KeyCode;
 impl Editor {
     pub fn edit_ascii_input(&mut self, key_code: KeyCode) {
         if let KeyCode::Char(k) = key_code {
-            self.buffer[self.cursor_index] = k as u8;
-            self.cursor_index += 1;
+            match self.charset.encode(k) {
+                Ok(bytes) => {
+                    // Bytes past the end of the file are added
+                    let length = if self.insert { 0 } else { cmp::min(bytes.len(), self.buffer.len() - self.cursor_index) };
+                    self.replace_bytes(self.cursor_index, length, &bytes, true);
+                    self.cursor_index += bytes.len();
+                }
+                Err(e) => self.show_error(e),
+            }
             self.refresh = true;
         }
         match key_code {
             KeyCode::Esc => {
+                self.history.seal();
                 self.mode = Mode::Normal;
                 self.refresh = true;
             }
-            KeyCode::Left => {
-                if self.cursor_index > 0 {
-                    self.nibble_index = 0;
-                    self.cursor_index -= 1;
-                    self.refresh = true;
-                }
+            KeyCode::Insert => {
+                self.insert = !self.insert;
+                self.refresh = true;
             }
-            KeyCode::Down => {
-                if self.cursor_index < self.buffer.len() - 16 {
-                    self.cursor_index += 16;
-                    self.nibble_index = 0;
-                    self.refresh = true;
-                }
+            KeyCode::Backspace => self.delete_backward(),
+            KeyCode::Left if self.cursor_index > 0 => {
+                self.nibble_index = 0;
+                self.cursor_index -= 1;
+                self.refresh = true;
             }
-            KeyCode::Up => {
-                if self.cursor_index >= 16 {
-                    self.nibble_index = 0;
-                    self.cursor_index -= 16;
-                    self.refresh = true;
-                }
+            KeyCode::Down if self.cursor_index < self.buffer.len().saturating_sub(self.bytes_per_line) => {
+                self.cursor_index += self.bytes_per_line;
+                self.nibble_index = 0;
+                self.refresh = true;
             }
-            KeyCode::Right => {
-                if self.cursor_index < self.buffer.len() - 1 {
-                    self.nibble_index = 0;
-                    self.cursor_index += 1;
-                    self.refresh = true;
-                }
+            KeyCode::Up if self.cursor_index >= self.bytes_per_line => {
+                self.nibble_index = 0;
+                self.cursor_index -= self.bytes_per_line;
+                self.refresh = true;
+            }
+            KeyCode::Right if self.cursor_index < self.last_position() => {
+                self.nibble_index = 0;
+                self.cursor_index += 1;
+                self.refresh = true;
             }
             _=> {}
         }
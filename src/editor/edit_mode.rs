@@ -1,38 +1,24 @@
-use crate::editor::{Editor, Mode, write_nibble};
+use crate::editor::{Editor, Mode};
 use crossterm::event::KeyCode;
 
 impl Editor {
     pub fn  edit_inputs(&mut self, key_code: KeyCode) {
         if let KeyCode::Char(k) = key_code {
-            if k as u8 >= 48 && k as u8 <= 57 {
-                let value = k as u8 - 48;
-
-                write_nibble(
-                    &mut self.buffer,
-                    self.cursor_index % 16 + 16 * (self.cursor_index / 16),
-                    value,
-                    self.nibble_index,
-                );
-                self.nibble_index += 1;
-                if self.nibble_index > 1 {
-                    self.nibble_index = 0;
-                    if self.cursor_index < self.buffer.len() - 1 {
-                        self.cursor_index += 1;
-                    }
-                }
-                self.refresh = true;
-            } else if k as u8 >= 97 && k as u8 <= 102 {
-                let value = k as u8 - 87;
-                write_nibble(
-                    &mut self.buffer,
-                    self.cursor_index % 16 + 16 * (self.cursor_index / 16),
-                    value,
-                    self.nibble_index,
-                );
+            let radix = self.layout.radix;
+            // The first digit of a byte inserts it, as well as any digit
+            // typed after the end of the file
+            let new_byte = (self.insert && self.nibble_index == 0) || self.cursor_index >= self.buffer.len();
+            if let (Some(value), false) = (radix.digit_value(k), self.buffer.is_empty() && !new_byte) {
+                let byte = if new_byte { 0 } else { self.buffer.get(self.cursor_index) };
+                let Some(byte) = radix.set_digit(byte, self.nibble_index as usize, value) else {
+                    self.show_error(format!("A byte can't be over 255 ({})", radix.name()));
+                    return;
+                };
+                self.replace_bytes(self.cursor_index, if new_byte { 0 } else { 1 }, &[byte], true);
                 self.nibble_index += 1;
-                if self.nibble_index > 1 {
+                if self.nibble_index as usize >= radix.digits() {
                     self.nibble_index = 0;
-                    if self.cursor_index < self.buffer.len() - 1 {
+                    if self.cursor_index < self.last_position() {
                         self.cursor_index += 1;
                     }
                 }
@@ -41,6 +27,9 @@ impl Editor {
         }
         match key_code {
             KeyCode::Esc => {
+                self.history.seal();
+                // A partly typed byte is left as it is
+                self.nibble_index = 0;
                 self.mode = Mode::Normal;
                 self.refresh = true;
             }
@@ -48,37 +37,49 @@ impl Editor {
                 self.mode = Mode::Help;
                 self.refresh = true;
             }
-            KeyCode::Char('q') => self.exit = true,
-            KeyCode::Char('h') | KeyCode::Left => {
-                if self.cursor_index > 0 {
-                    self.nibble_index = 0;
-                    self.cursor_index -= 1;
-                    self.refresh = true;
-                }
+            KeyCode::Insert => {
+                self.insert = !self.insert;
+                self.nibble_index = 0;
+                self.refresh = true;
             }
-            KeyCode::Char('j') | KeyCode::Down => {
-                if self.cursor_index < self.buffer.len() - 16 {
-                    self.cursor_index += 16;
-                    self.nibble_index = 0;
-                    self.refresh = true;
-                }
+            KeyCode::Backspace => self.delete_backward(),
+            KeyCode::Char('h') | KeyCode::Left if self.cursor_index > 0 => {
+                self.nibble_index = 0;
+                self.cursor_index -= 1;
+                self.refresh = true;
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                if self.cursor_index >= 16 {
-                    self.nibble_index = 0;
-                    self.cursor_index -= 16;
-                    self.refresh = true;
-                }
+            KeyCode::Char('j') | KeyCode::Down if self.cursor_index < self.buffer.len().saturating_sub(self.bytes_per_line) => {
+                self.cursor_index += self.bytes_per_line;
+                self.nibble_index = 0;
+                self.refresh = true;
             }
-            KeyCode::Char('l') | KeyCode::Right => {
-                if self.cursor_index < self.buffer.len() - 1 {
-                    self.nibble_index = 0;
-                    self.cursor_index += 1;
-                    self.refresh = true;
-                }
+            KeyCode::Char('k') | KeyCode::Up if self.cursor_index >= self.bytes_per_line => {
+                self.nibble_index = 0;
+                self.cursor_index -= self.bytes_per_line;
+                self.refresh = true;
+            }
+            KeyCode::Char('l') | KeyCode::Right if self.cursor_index < self.last_position() => {
+                self.nibble_index = 0;
+                self.cursor_index += 1;
+                self.refresh = true;
             }
             _ => {}
         }
     }
 
+    /// Backspace when inserting: delete the byte being typed, or else the
+    /// previous one.
+    pub fn delete_backward(&mut self) {
+        if !self.insert {
+            return;
+        }
+        if self.nibble_index > 0 {
+            self.replace_bytes(self.cursor_index, 1, &[], true);
+            self.nibble_index = 0;
+        } else if self.cursor_index > 0 {
+            self.cursor_index -= 1;
+            self.replace_bytes(self.cursor_index, 1, &[], true);
+        }
+        self.refresh = true;
+    }
 }
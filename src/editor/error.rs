@@ -0,0 +1,31 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Failures reported to the user instead of stopping the editor.
+#[derive(Debug)]
+pub enum EditorError {
+    Open(String, io::Error),
+    Read(String, io::Error),
+    Write(String, io::Error),
+    Terminal(io::Error),
+}
+
+impl fmt::Display for EditorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EditorError::Open(file_name, e) => write!(f, "Cannot open {}: {}", file_name, e),
+            EditorError::Read(file_name, e) => write!(f, "Cannot read {}: {}", file_name, e),
+            EditorError::Write(file_name, e) => write!(f, "Cannot write {}: {}", file_name, e),
+            EditorError::Terminal(e) => write!(f, "Terminal error: {}", e),
+        }
+    }
+}
+
+impl Error for EditorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            EditorError::Open(_, e) | EditorError::Read(_, e) | EditorError::Write(_, e) | EditorError::Terminal(e) => Some(e),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crate::editor::number::parse_integer;
+
+/// Evaluate an expression like `0x40*3+8`, `end-0x10` or `'a+4`.
+///
+/// The numbers are decimal, or hex, octal or binary with a `0x`, `0o` or
+/// `0b` prefix, and `name` gives the value of the names (`cur`, `end` and
+/// the marks).
+pub fn evaluate(text: &str, name: &dyn Fn(&str) -> Result<i128, String>) -> Result<i128, String> {
+    let mut parser = Parser { chars: text.chars().peekable(), name };
+    let value = parser.sum()?;
+    parser.skip_spaces();
+    match parser.chars.next() {
+        Some(c) => Err(format!("Unexpected '{}'", c)),
+        None => Ok(value),
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+    name: &'a dyn Fn(&str) -> Result<i128, String>,
+}
+
+impl Parser<'_> {
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    // Terms added or subtracted
+    fn sum(&mut self) -> Result<i128, String> {
+        let mut value = self.product()?;
+        loop {
+            self.skip_spaces();
+            let value_after = match self.chars.peek() {
+                Some('+') => {
+                    self.chars.next();
+                    value.checked_add(self.product()?)
+                }
+                Some('-') => {
+                    self.chars.next();
+                    value.checked_sub(self.product()?)
+                }
+                _ => return Ok(value),
+            };
+            value = value_after.ok_or("Address too large")?;
+        }
+    }
+
+    // Factors multiplied or divided
+    fn product(&mut self) -> Result<i128, String> {
+        let mut value = self.factor()?;
+        loop {
+            self.skip_spaces();
+            let operator = match self.chars.peek() {
+                Some(c @ ('*' | '/' | '%')) => *c,
+                _ => return Ok(value),
+            };
+            self.chars.next();
+            let factor = self.factor()?;
+            if operator != '*' && factor == 0 {
+                return Err("Division by zero".to_string());
+            }
+            let value_after = match operator {
+                '*' => value.checked_mul(factor),
+                '/' => value.checked_div(factor),
+                _ => value.checked_rem(factor),
+            };
+            value = value_after.ok_or("Address too large")?;
+        }
+    }
+
+    fn factor(&mut self) -> Result<i128, String> {
+        self.skip_spaces();
+        match self.chars.next() {
+            Some('+') => self.factor(),
+            Some('-') => self.factor()?.checked_neg().ok_or("Address too large".to_string()),
+            Some('(') => {
+                let value = self.sum()?;
+                self.skip_spaces();
+                match self.chars.next() {
+                    Some(')') => Ok(value),
+                    _ => Err("Missing ')'".to_string()),
+                }
+            }
+            // A mark, as typed to go to it
+            Some('\'') => match self.chars.next() {
+                Some(c) => (self.name)(&c.to_string()),
+                None => Err("Missing mark name after '''".to_string()),
+            },
+            Some(c) if c.is_ascii_digit() => {
+                let mut number = c.to_string();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    number.push(c);
+                }
+                parse_integer(&number).ok_or(format!("Invalid number '{}'", number))
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let mut word = c.to_string();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphanumeric()) {
+                    word.push(c);
+                }
+                (self.name)(&word)
+            }
+            Some(c) => Err(format!("Unexpected '{}'", c)),
+            None => Err("Missing value".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(name: &str) -> Result<i128, String> {
+        match name {
+            "cur" => Ok(0x100),
+            "end" => Ok(0x1000),
+            "a" => Ok(42),
+            _ => Err(format!("Unknown name '{}'", name)),
+        }
+    }
+
+    fn eval(text: &str) -> Result<i128, String> {
+        evaluate(text, &names)
+    }
+
+    #[test]
+    fn precedence_and_parentheses() {
+        assert_eq!(eval("0x40*3+8"), Ok(0xc8));
+        assert_eq!(eval("2+3*4"), Ok(14));
+        assert_eq!(eval("(2+3)*4"), Ok(20));
+        assert_eq!(eval("20-4-6"), Ok(10));
+        assert_eq!(eval("100/10/5"), Ok(2));
+        assert_eq!(eval("17%5*2"), Ok(4));
+        assert_eq!(eval(" 1 + ( 2 * ( 3 + 4 ) ) "), Ok(15));
+        assert_eq!(eval("-16"), Ok(-16));
+        assert_eq!(eval("--16"), Ok(16));
+        assert_eq!(eval("+0x200"), Ok(0x200));
+        assert_eq!(eval("2*-3"), Ok(-6));
+    }
+
+    #[test]
+    fn names_and_marks() {
+        assert_eq!(eval("end-0x10"), Ok(0xff0));
+        assert_eq!(eval("cur+end"), Ok(0x1100));
+        assert_eq!(eval("'a+4"), Ok(46));
+        assert_eq!(eval("a"), Ok(42));
+        assert_eq!(eval("'b"), Err("Unknown name 'b'".to_string()));
+        assert_eq!(eval("foo"), Err("Unknown name 'foo'".to_string()));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(eval(""), Err("Missing value".to_string()));
+        assert_eq!(eval("1+"), Err("Missing value".to_string()));
+        assert_eq!(eval("(1+2"), Err("Missing ')'".to_string()));
+        assert_eq!(eval("1+2)"), Err("Unexpected ')'".to_string()));
+        assert_eq!(eval("4/0"), Err("Division by zero".to_string()));
+        assert_eq!(eval("4%(1-1)"), Err("Division by zero".to_string()));
+        assert_eq!(eval("12z"), Err("Invalid number '12z'".to_string()));
+        assert_eq!(eval("'"), Err("Missing mark name after '''".to_string()));
+        assert!(eval(&format!("{}*2", i128::MAX)).is_err());
+        assert!(eval(&format!("-{}-2", i128::MAX)).is_err());
+    }
+
+    #[test]
+    fn random_expressions_as_rust() {
+        let mut seed: u64 = 5;
+        let mut random = |bound: i128| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as i128 % bound
+        };
+        for _ in 0..500 {
+            let (a, b, c, d) = (random(1000), random(1000) + 1, random(1000), random(1000) + 1);
+            assert_eq!(eval(&format!("{}*{}+{}/{}", a, b, c, d)), Ok(a * b + c / d));
+            assert_eq!(eval(&format!("({}-{})*{}%{}", a, b, c, d)), Ok((a - b) * c % d));
+            assert_eq!(eval(&format!("{:#x}-{:#o}*-{:#b}", a, b, c)), Ok(a - b * -c));
+        }
+    }
+}
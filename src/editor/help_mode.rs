@@ -1,14 +1,52 @@
+use std::cmp;
+
 use crossterm::event::KeyCode;
-use crate::editor::{Editor, Mode};
+use crossterm::style::Stylize;
+use crate::editor::{Editor, Mode, HELP};
+use crate::editor::screen::Frame;
+
+const HELP_WIDTH: usize = 74;
+
 impl Editor {
     pub fn help_inputs(&mut self, key_code: KeyCode) {
         match key_code {
             KeyCode::Esc | KeyCode::Char('?') => {
+                self.help_page = 0;
                 self.mode = Mode::Normal;
-                self.refresh = true;
+            }
+            KeyCode::Char('n') | KeyCode::Char(' ') | KeyCode::Down | KeyCode::PageDown => {
+                self.help_page = cmp::min(self.help_page + 1, self.help_pages() - 1);
+            }
+            KeyCode::Char('b') | KeyCode::Up | KeyCode::PageUp => {
+                self.help_page = cmp::min(self.help_page, self.help_pages() - 1).saturating_sub(1);
             }
             _ => {}
         }
+        self.refresh = true;
+    }
+
+    fn help_pages(&self) -> usize {
+        HELP.len().div_ceil(self.help_rows)
+    }
 
+    /// Draw the page of the help which fits in the terminal, over the bytes.
+    pub fn render_help(&self, frame: &mut Frame, top: u16) {
+        let pages = self.help_pages();
+        let page = cmp::min(self.help_page, pages - 1);
+        let mut row = top;
+        for line_text in HELP.iter().skip(page * self.help_rows).take(self.help_rows) {
+            frame.move_to(5, row);
+            frame.print(line_text.white());
+            row += 1;
+        }
+        let footer = if pages > 1 {
+            format!("<ESC> or '?' to quit help, n / b: next / previous page ({}/{})", page + 1, pages)
+        } else {
+            "<ESC> or '?' to quit help".to_string()
+        };
+        frame.move_to(5, row);
+        frame.print(" ".repeat(HELP_WIDTH).white());
+        frame.move_to(5, row + 1);
+        frame.print(format!("{:^width$}", footer, width = HELP_WIDTH).white());
     }
 }
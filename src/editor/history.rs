@@ -0,0 +1,403 @@
+use crate::editor::Editor;
+use crate::editor::buffer::Span;
+
+/// A single reversible modification of the buffer: at `offset`, the bytes
+/// `old` have been replaced by the bytes `new`.
+pub struct Change {
+    pub offset: usize,
+    pub old: Span,
+    pub new: Span,
+    // Cursor position before the change, restored on undo
+    pub cursor: usize,
+}
+
+impl Change {
+    pub fn new(offset: usize, old: Span, new: Span, cursor: usize) -> Change {
+        Change {
+            offset,
+            old,
+            new,
+            cursor,
+        }
+    }
+
+    // Try to extend this change with the one that follows it, so that a run
+    // of keystrokes can be undone in one step
+    fn merge(&mut self, next: &Change) -> bool {
+        // Insertions: the next byte is inserted after the inserted ones,
+        // or changes one of them, or deletes the last one
+        if self.old.is_empty() {
+            let end = self.offset + self.new.len();
+            if next.old.is_empty() && next.offset == end {
+                self.new.append(&next.new);
+                return true;
+            }
+            if next.offset >= self.offset && next.offset + next.old.len() <= end {
+                if next.old.len() == next.new.len() {
+                    self.new = overwrite(&self.new, next.offset - self.offset, &next.new);
+                    return true;
+                }
+                if next.new.is_empty() && next.offset + next.old.len() == end {
+                    self.new = self.new.slice(0, next.offset - self.offset);
+                    return true;
+                }
+            }
+        }
+        // Deletions: the next one deletes the bytes right before, as
+        // Backspace does
+        if self.new.is_empty() && next.new.is_empty() && next.offset + next.old.len() == self.offset {
+            let mut old = next.old.clone();
+            old.append(&self.old);
+            self.old = old;
+            self.offset = next.offset;
+            return true;
+        }
+        // Overwrites: the next write lands inside or right after this one,
+        // the bytes it writes past the end of this one are added to both
+        if self.old.len() == self.new.len()
+            && next.old.len() == next.new.len()
+            && next.offset >= self.offset
+            && next.offset <= self.offset + self.new.len()
+        {
+            let relative = next.offset - self.offset;
+            let overlap = self.new.len() - relative;
+            self.new = overwrite(&self.new, relative, &next.new);
+            self.old.append(&next.old.slice(overlap, next.old.len()));
+            return true;
+        }
+        false
+    }
+}
+
+// `span` with the bytes from `relative` replaced by `bytes`, which may go past its end
+fn overwrite(span: &Span, relative: usize, bytes: &Span) -> Span {
+    let mut result = span.slice(0, relative);
+    result.append(bytes);
+    result.append(&span.slice(relative + bytes.len(), span.len()));
+    result
+}
+
+/// Follow a change of `old_length` bytes at `offset` into `new_length`
+/// bytes, for an offset kept across it like a mark. An offset on deleted
+/// bytes goes to the first byte after them.
+pub fn shift_offset(kept: &mut usize, offset: usize, old_length: usize, new_length: usize) {
+    if *kept >= offset + old_length {
+        *kept = *kept + new_length - old_length;
+    } else if *kept >= offset + new_length {
+        *kept = offset + new_length;
+    }
+}
+
+// Changes undone and redone together
+struct Step {
+    id: usize,
+    changes: Vec<Change>,
+}
+
+pub struct History {
+    undo_stack: Vec<Step>,
+    redo_stack: Vec<Step>,
+    grouping: bool,
+    batching: bool,
+    next_id: usize,
+    // Last step applied when the file was written, None for the file as opened
+    saved: Option<usize>,
+    // Number of changes ever recorded
+    recorded: usize,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            grouping: false,
+            batching: false,
+            next_id: 0,
+            saved: None,
+            recorded: 0,
+        }
+    }
+
+    /// Record a change. With `group`, the change is merged into the previous
+    /// one if that one was also recorded as part of a group.
+    pub fn record(&mut self, change: Change, group: bool) {
+        self.redo_stack.clear();
+        self.recorded += 1;
+        if self.batching {
+            if let Some(step) = self.undo_stack.last_mut() {
+                step.changes.push(change);
+                return;
+            }
+        }
+        let merged = match self.undo_stack.last_mut().and_then(|step| step.changes.last_mut()) {
+            Some(last) if group && self.grouping => last.merge(&change),
+            _ => false,
+        };
+        if !merged {
+            self.push_step(vec![change]);
+        }
+        self.grouping = group;
+    }
+
+    /// Close the current group: the next change will be a new undo step.
+    pub fn seal(&mut self) {
+        self.grouping = false;
+    }
+
+    /// Record all the changes until `end_batch` as a single undo step.
+    pub fn begin_batch(&mut self) {
+        self.grouping = false;
+        self.push_step(Vec::new());
+        self.batching = true;
+    }
+
+    pub fn end_batch(&mut self) {
+        self.batching = false;
+        if self.undo_stack.last().is_some_and(|step| step.changes.is_empty()) {
+            self.undo_stack.pop();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.grouping = false;
+        self.batching = false;
+        self.saved = None;
+    }
+
+    /// Remember the current state as the one written on the disk.
+    pub fn mark_saved(&mut self) {
+        self.grouping = false;
+        self.saved = self.undo_stack.last().map(|step| step.id);
+    }
+
+    /// Number of changes recorded since the file was opened, to tell
+    /// whether a command changed the buffer.
+    pub fn recorded(&self) -> usize {
+        self.recorded
+    }
+
+    /// Whether the buffer differs from the file on the disk.
+    pub fn is_modified(&self) -> bool {
+        self.undo_stack.last().map(|step| step.id) != self.saved
+    }
+
+    fn push_step(&mut self, changes: Vec<Change>) {
+        self.undo_stack.push(Step {
+            id: self.next_id,
+            changes,
+        });
+        self.next_id += 1;
+    }
+
+    fn undo(&mut self) -> Option<&Step> {
+        self.grouping = false;
+        let step = self.undo_stack.pop()?;
+        self.redo_stack.push(step);
+        self.redo_stack.last()
+    }
+
+    fn redo(&mut self) -> Option<&Step> {
+        self.grouping = false;
+        let step = self.redo_stack.pop()?;
+        self.undo_stack.push(step);
+        self.undo_stack.last()
+    }
+}
+
+impl Editor {
+    /// Undo the last step, returning false if there was none.
+    pub fn undo(&mut self) -> bool {
+        let Some(step) = self.history.undo() else {
+            return false;
+        };
+        // Last change first, to find each one at its own offset
+        for change in step.changes.iter().rev() {
+            self.buffer.replace_span(change.offset, change.new.len(), &change.old);
+            self.marks.shift(change.offset, change.new.len(), change.old.len());
+            self.jumps.shift(change.offset, change.new.len(), change.old.len());
+        }
+        self.cursor_index = step.changes[0].cursor;
+        self.nibble_index = 0;
+        self.refresh = true;
+        true
+    }
+
+    /// Redo the last undone step, returning false if there was none.
+    pub fn redo(&mut self) -> bool {
+        let Some(step) = self.history.redo() else {
+            return false;
+        };
+        for change in step.changes.iter() {
+            self.buffer.replace_span(change.offset, change.old.len(), &change.new);
+            self.marks.shift(change.offset, change.old.len(), change.new.len());
+            self.jumps.shift(change.offset, change.old.len(), change.new.len());
+        }
+        self.cursor_index = step.changes[0].cursor;
+        self.nibble_index = 0;
+        self.refresh = true;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::editor::buffer::tests::buffer_with;
+    use crate::editor::buffer::Buffer;
+
+    fn content(buffer: &Buffer) -> Vec<u8> {
+        buffer.slice(0, buffer.len())
+    }
+
+    // As the editor does it
+    fn replace(buffer: &mut Buffer, history: &mut History, offset: usize, length: usize, bytes: &[u8], group: bool) {
+        let old = buffer.replace(offset, length, bytes);
+        let new = buffer.span(offset, bytes.len());
+        history.record(Change::new(offset, old, new, offset), group);
+    }
+
+    fn undo(buffer: &mut Buffer, history: &mut History) -> bool {
+        let Some(step) = history.undo() else {
+            return false;
+        };
+        for change in step.changes.iter().rev() {
+            buffer.replace_span(change.offset, change.new.len(), &change.old);
+        }
+        true
+    }
+
+    fn redo(buffer: &mut Buffer, history: &mut History) -> bool {
+        let Some(step) = history.redo() else {
+            return false;
+        };
+        for change in step.changes.iter() {
+            buffer.replace_span(change.offset, change.old.len(), &change.new);
+        }
+        true
+    }
+
+    #[test]
+    fn typed_insertion_is_one_step() {
+        let mut buffer = buffer_with("history-insert", b"0123456789");
+        let mut history = History::new();
+        for (k, byte) in b"abcd".iter().enumerate() {
+            replace(&mut buffer, &mut history, 3 + k, 0, &[*byte], true);
+        }
+        // A nibble typed again, then Backspace
+        replace(&mut buffer, &mut history, 4, 1, b"B", true);
+        replace(&mut buffer, &mut history, 6, 1, b"", true);
+        assert_eq!(content(&buffer), b"012aBc3456789");
+
+        assert!(undo(&mut buffer, &mut history));
+        assert_eq!(content(&buffer), b"0123456789");
+        assert!(!undo(&mut buffer, &mut history));
+        assert!(redo(&mut buffer, &mut history));
+        assert_eq!(content(&buffer), b"012aBc3456789");
+    }
+
+    #[test]
+    fn backspaces_are_one_step() {
+        let mut buffer = buffer_with("history-backspace", b"0123456789");
+        let mut history = History::new();
+        for offset in (2..6).rev() {
+            replace(&mut buffer, &mut history, offset, 1, b"", true);
+        }
+        assert_eq!(content(&buffer), b"016789");
+        assert!(undo(&mut buffer, &mut history));
+        assert_eq!(content(&buffer), b"0123456789");
+    }
+
+    #[test]
+    fn overwrite_past_the_end_is_one_step() {
+        let mut buffer = buffer_with("history-overwrite", b"0123456789");
+        let mut history = History::new();
+        for (k, byte) in b"wxy".iter().enumerate() {
+            replace(&mut buffer, &mut history, 7 + k, 1, &[*byte], true);
+        }
+        // Overwriting a byte again, then inserting at the end
+        replace(&mut buffer, &mut history, 8, 1, b"X", true);
+        replace(&mut buffer, &mut history, 10, 0, b"z", true);
+        assert_eq!(content(&buffer), b"0123456wXyz");
+
+        assert!(undo(&mut buffer, &mut history));
+        assert_eq!(content(&buffer), b"0123456wXy");
+        assert!(undo(&mut buffer, &mut history));
+        assert_eq!(content(&buffer), b"0123456789");
+    }
+
+    #[test]
+    fn ungrouped_and_sealed_changes_are_separate_steps() {
+        let mut buffer = buffer_with("history-seal", b"0123");
+        let mut history = History::new();
+        replace(&mut buffer, &mut history, 0, 1, b"a", false);
+        replace(&mut buffer, &mut history, 1, 1, b"b", true);
+        history.seal();
+        replace(&mut buffer, &mut history, 2, 1, b"c", true);
+        history.begin_batch();
+        replace(&mut buffer, &mut history, 0, 1, b"", false);
+        replace(&mut buffer, &mut history, 0, 1, b"", false);
+        history.end_batch();
+        assert_eq!(content(&buffer), b"c3");
+
+        let mut states = Vec::new();
+        while undo(&mut buffer, &mut history) {
+            states.push(content(&buffer));
+        }
+        assert_eq!(states, [&b"abc3"[..], b"ab23", b"a123", b"0123"]);
+    }
+
+    #[test]
+    fn random_changes_undo_step_by_step() {
+        let original: Vec<u8> = (0..64).collect();
+        let mut buffer = buffer_with("history-random", &original);
+        let mut history = History::new();
+        // The content before each step, as the naive history
+        let mut states = Vec::new();
+        let mut seed: u64 = 3;
+        let mut random = |bound: usize| {
+            seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % bound
+        };
+        for _ in 0..400 {
+            let before = content(&buffer);
+            let steps = history.undo_stack.len();
+            // Mostly small changes next to each other, as typed
+            let offset = random(buffer.len() + 1);
+            let length = random(buffer.len() - offset + 1).min(random(3));
+            let bytes = vec![random(256) as u8; random(3)];
+            let group = random(4) != 0;
+            replace(&mut buffer, &mut history, offset, length, &bytes, group);
+            if history.undo_stack.len() > steps {
+                states.push(before);
+            }
+        }
+        let last = content(&buffer);
+
+        while let Some(state) = states.pop() {
+            assert!(undo(&mut buffer, &mut history));
+            assert_eq!(content(&buffer), state);
+        }
+        assert!(!undo(&mut buffer, &mut history));
+        assert_eq!(content(&buffer), original);
+
+        while redo(&mut buffer, &mut history) {}
+        assert_eq!(content(&buffer), last);
+    }
+
+    #[test]
+    fn shift_offset_follows_the_bytes() {
+        let shifted = |kept: usize, offset, old_length, new_length| {
+            let mut kept = kept;
+            shift_offset(&mut kept, offset, old_length, new_length);
+            kept
+        };
+        assert_eq!(shifted(3, 5, 2, 0), 3);
+        assert_eq!(shifted(10, 5, 2, 0), 8);
+        assert_eq!(shifted(10, 5, 0, 4), 14);
+        assert_eq!(shifted(6, 5, 3, 0), 5);
+        assert_eq!(shifted(6, 5, 3, 3), 6);
+    }
+}
@@ -0,0 +1,188 @@
+use std::cmp;
+
+use crossterm::event::KeyCode;
+use crossterm::style::Stylize;
+use crate::editor::{Editor, Mode};
+use crate::editor::screen::Frame;
+use crate::editor::number::{Endianness, NumberType, NUMBER_TYPES};
+
+const LABEL_WIDTH: u16 = 8;
+const VALUE_WIDTH: u16 = 26;
+// Columns taken by the pane, with its margin
+pub const INSPECTOR_WIDTH: usize = 3 + (LABEL_WIDTH + 2 * VALUE_WIDTH) as usize;
+
+impl Editor {
+    /// The type of the selected row of the inspector.
+    pub fn inspector_type(&self) -> NumberType {
+        NUMBER_TYPES[self.inspector_row]
+    }
+
+    pub fn inspector_inputs(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                self.refresh = true;
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.inspector_row = cmp::min(self.inspector_row + 1, NUMBER_TYPES.len() - 1);
+                self.refresh = true;
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.inspector_row = self.inspector_row.saturating_sub(1);
+                self.refresh = true;
+            }
+            KeyCode::Char('h') | KeyCode::Left => {
+                self.inspector_endianness = Endianness::Little;
+                self.refresh = true;
+            }
+            KeyCode::Char('l') | KeyCode::Right => {
+                self.inspector_endianness = Endianness::Big;
+                self.refresh = true;
+            }
+            KeyCode::Enter => {
+                self.input.clear();
+                self.mode = Mode::InspectorEdit;
+                self.refresh = true;
+            }
+            _ => {}
+        }
+    }
+
+    // Typing a new value for the selected type
+    pub fn inspector_edit_inputs(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = Mode::Inspector;
+                self.refresh = true;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.refresh = true;
+            }
+            KeyCode::Enter => {
+                let number_type = self.inspector_type();
+                match number_type.encode(&self.input, self.inspector_endianness) {
+                    Ok(_) if self.cursor_index + number_type.size() > self.buffer.len() => {
+                        self.show_error(format!("Not enough bytes for a {}", number_type.name()));
+                    }
+                    Ok(bytes) => {
+                        self.replace_bytes(self.cursor_index, bytes.len(), &bytes, false);
+                    }
+                    Err(e) => self.show_error(e),
+                }
+                self.mode = Mode::Inspector;
+                self.refresh = true;
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.refresh = true;
+            }
+            _ => {}
+        }
+    }
+
+    /// Draw the values of the bytes under the cursor, from `top` at `column`.
+    pub fn render_inspector(&self, frame: &mut Frame, column: u16, top: u16) {
+        let bytes = self.buffer.slice(self.cursor_index, cmp::min(self.cursor_index + 8, self.buffer.len()));
+        let value_column = |endianness: Endianness| match endianness {
+            Endianness::Little => column + LABEL_WIDTH,
+            Endianness::Big => column + LABEL_WIDTH + VALUE_WIDTH,
+        };
+
+        frame.move_to(value_column(Endianness::Little), top);
+        frame.print("little endian".green());
+        frame.move_to(value_column(Endianness::Big), top);
+        frame.print("big endian".green());
+
+        let mut line = top + 1;
+        for (row, number_type) in NUMBER_TYPES.iter().enumerate() {
+            frame.move_to(column, line);
+            frame.print(number_type.name().green());
+            for endianness in [Endianness::Little, Endianness::Big] {
+                let selected = row == self.inspector_row && endianness == self.inspector_endianness;
+                let text = if selected && self.mode == Mode::InspectorEdit {
+                    format!("{}_", self.input)
+                } else {
+                    number_type.decode(&bytes, endianness).unwrap_or("-".to_string())
+                };
+                let text = fit(text);
+                let styled = if selected && (self.mode == Mode::Inspector || self.mode == Mode::InspectorEdit) {
+                    text.dark_grey().on_magenta()
+                } else {
+                    text.magenta()
+                };
+                frame.move_to(value_column(endianness), line);
+                frame.print(styled);
+            }
+            line += 1;
+        }
+
+        // Date and time values, and bits of the byte under the cursor
+        let mut others: Vec<(&str, String, String)> = Vec::new();
+        if bytes.len() >= 4 {
+            let le = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            let be = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            others.push(("unix", unix_time(le as i64), unix_time(be as i64)));
+            others.push((
+                "dos",
+                dos_time(u16::from_le_bytes([bytes[0], bytes[1]]), u16::from_le_bytes([bytes[2], bytes[3]])),
+                dos_time(u16::from_be_bytes([bytes[0], bytes[1]]), u16::from_be_bytes([bytes[2], bytes[3]])),
+            ));
+        }
+        if let Some(byte) = bytes.first() {
+            others.push(("bits", format!("{:08b}", byte), String::new()));
+        }
+        for (label, le, be) in others {
+            frame.move_to(column, line);
+            frame.print(label.green());
+            frame.move_to(value_column(Endianness::Little), line);
+            frame.print(le.magenta());
+            frame.move_to(value_column(Endianness::Big), line);
+            frame.print(be.magenta());
+            line += 1;
+        }
+    }
+}
+
+// Cut a value to the width of its column
+fn fit(text: String) -> String {
+    text.chars().take(VALUE_WIDTH as usize - 2).collect()
+}
+
+// UTC date and time of a number of seconds since 1970-01-01
+fn unix_time(seconds: i64) -> String {
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86400));
+    let time = seconds.rem_euclid(86400);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year, month, day, time / 3600, time % 3600 / 60, time % 60
+    )
+}
+
+// MS-DOS time (2 seconds precision) followed by the date, as in FAT and ZIP
+fn dos_time(time: u16, date: u16) -> String {
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        1980 + (date >> 9),
+        (date >> 5) & 0x0f,
+        date & 0x1f,
+        time >> 11,
+        (time >> 5) & 0x3f,
+        (time & 0x1f) * 2
+    )
+}
+
+// Year, month and day of a number of days since 1970-01-01
+// (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
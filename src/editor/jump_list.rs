@@ -0,0 +1,90 @@
+use crate::editor::Editor;
+use crate::editor::history::shift_offset;
+
+// Older jumps are forgotten
+const MAX_JUMPS: usize = 100;
+
+/// Where the cursor was before the jumps, to go back with CTRL-o and
+/// forward again with CTRL-i.
+#[derive(Default)]
+pub struct JumpList {
+    offsets: Vec<usize>,
+    // Position in the list while moving through it, its length otherwise
+    index: usize,
+    // Where the followed pointers were read
+    returns: Vec<usize>,
+}
+
+impl JumpList {
+    /// Remember the offset the cursor jumps from. The jumps which were
+    /// gone back over are dropped.
+    pub fn push(&mut self, from: usize) {
+        self.offsets.truncate(self.index);
+        self.offsets.retain(|offset| *offset != from);
+        self.offsets.push(from);
+        if self.offsets.len() > MAX_JUMPS {
+            self.offsets.remove(0);
+        }
+        self.index = self.offsets.len();
+    }
+
+    pub fn back(&mut self, current: usize) -> Option<usize> {
+        // The current position is kept to come back to it
+        if self.index == self.offsets.len() && self.offsets.last() != Some(&current) {
+            self.offsets.push(current);
+        }
+        self.index = self.index.min(self.offsets.len() - 1).checked_sub(1)?;
+        Some(self.offsets[self.index])
+    }
+
+    pub fn forward(&mut self) -> Option<usize> {
+        if self.index + 1 >= self.offsets.len() {
+            return None;
+        }
+        self.index += 1;
+        Some(self.offsets[self.index])
+    }
+
+    pub fn push_return(&mut self, from: usize) {
+        self.returns.push(from);
+    }
+
+    pub fn pop_return(&mut self) -> Option<usize> {
+        self.returns.pop()
+    }
+
+    /// Follow a change of the bytes, as the marks do.
+    pub fn shift(&mut self, offset: usize, old_length: usize, new_length: usize) {
+        for jump in self.offsets.iter_mut().chain(self.returns.iter_mut()) {
+            shift_offset(jump, offset, old_length, new_length);
+        }
+    }
+}
+
+impl Editor {
+    /// Move the cursor to `offset`, remembering where it was.
+    pub fn jump_to(&mut self, offset: usize) {
+        if offset != self.cursor_index {
+            self.jumps.push(self.cursor_index);
+        }
+        self.cursor_index = offset;
+        self.nibble_index = 0;
+        self.refresh = true;
+    }
+
+    pub fn jump_back(&mut self) {
+        if let Some(offset) = self.jumps.back(self.cursor_index) {
+            self.cursor_index = offset;
+            self.nibble_index = 0;
+            self.refresh = true;
+        }
+    }
+
+    pub fn jump_forward(&mut self) {
+        if let Some(offset) = self.jumps.forward() {
+            self.cursor_index = offset;
+            self.nibble_index = 0;
+            self.refresh = true;
+        }
+    }
+}
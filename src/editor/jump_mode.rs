@@ -1,42 +1,30 @@
 use crossterm::event::KeyCode;
-use crate::editor::{Editor, Mode};
+use crate::editor::Editor;
 impl Editor {
     pub fn jump_inputs(&mut self, key_code: KeyCode) {
         match key_code {
             KeyCode::Esc => {
-                self.mode = Mode::Normal;
+                self.mode = self.jump_return;
                 self.refresh = true;
             }
-            KeyCode::Char('q') => self.exit = true,
             KeyCode::Enter => {
-                self.cursor_index = self.jump_adress as usize;
-                self.jump_adress = 0;
-                self.mode = Mode::Normal;
+                // The mode is left first, for the error to be shown
+                self.mode = self.jump_return;
+                let expression = self.input.clone();
+                if !expression.trim().is_empty() {
+                    self.run_command(&format!("goto {}", expression));
+                }
                 self.refresh = true;
             }
             KeyCode::Backspace => {
-                self.jump_adress >>= 4;
+                self.input.pop();
                 self.refresh = true;
             }
-            _ => {}
-        }
-
-        // Process the adress input 
-        if let KeyCode::Char(k) = key_code {
-
-            // For digit 0 to 9 
-            if k as u32 >= 48 && k as u32 <= 57 {
-                let value = k as u32 - 48;
-                self.jump_adress <<= 4;
-                self.jump_adress += value;
-            }
-            // For digit a to f
-            else if k as u32 >= 97 && k as u32 <= 102 {
-                let value = k as u32 - 87;
-                self.jump_adress <<= 4;
-                self.jump_adress += value;
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.refresh = true;
             }
-            self.refresh = true;
+            _ => {}
         }
     }
 }
@@ -0,0 +1,95 @@
+use crate::editor::radix::Radix;
+
+// Width of the address at the start of a line: "00000000 : "
+const ADDRESS_WIDTH: usize = 11;
+
+const WIDTHS: [LineWidth; 5] = [
+    LineWidth::Bytes(8),
+    LineWidth::Bytes(16),
+    LineWidth::Bytes(24),
+    LineWidth::Bytes(32),
+    LineWidth::Auto,
+];
+const GROUPS: [usize; 4] = [1, 2, 4, 8];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LineWidth {
+    Bytes(usize),
+    // As many bytes as the terminal width allows, by 8
+    Auto,
+}
+
+/// How the bytes are laid out on the lines of the grid.
+pub struct Layout {
+    pub width: LineWidth,
+    // Bytes shown without a space between them
+    pub group: usize,
+    // An extra space every 8 bytes
+    pub gap: bool,
+    pub radix: Radix,
+}
+
+impl Default for Layout {
+    fn default() -> Layout {
+        Layout {
+            width: LineWidth::Bytes(16),
+            group: 1,
+            gap: false,
+            radix: Radix::Hex,
+        }
+    }
+}
+
+impl Layout {
+    pub fn next_width(&mut self) {
+        let index = WIDTHS.iter().position(|w| *w == self.width).unwrap_or(0);
+        self.width = WIDTHS[(index + 1) % WIDTHS.len()];
+    }
+
+    pub fn next_group(&mut self) {
+        let index = GROUPS.iter().position(|g| *g == self.group).unwrap_or(0);
+        self.group = GROUPS[(index + 1) % GROUPS.len()];
+    }
+
+    pub fn name(&self) -> String {
+        let width = match self.width {
+            LineWidth::Bytes(bytes) => format!("{} bytes per line", bytes),
+            LineWidth::Auto => "auto width".to_string(),
+        };
+        let gap = if self.gap { ", gap every 8 bytes" } else { "" };
+        format!("{}, groups of {}{}", width, self.group, gap)
+    }
+
+    /// Bytes on a line, `columns` being the width left for the grid.
+    pub fn bytes_per_line(&self, columns: usize) -> usize {
+        match self.width {
+            LineWidth::Bytes(bytes) => bytes,
+            LineWidth::Auto => {
+                let mut bytes = 8;
+                while self.ascii_column(bytes + 8) + 3 + bytes + 8 <= columns {
+                    bytes += 8;
+                }
+                bytes
+            }
+        }
+    }
+
+    /// Text after the byte at `column` of a line of `bytes_per_line` bytes.
+    pub fn separator(&self, column: usize, bytes_per_line: usize) -> &str {
+        let end_of_group = (column + 1).is_multiple_of(self.group) || column + 1 == bytes_per_line;
+        let gap = self.gap && (column + 1).is_multiple_of(8) && column + 1 < bytes_per_line;
+        match (end_of_group, gap) {
+            (true, true) => "  ",
+            (true, false) => " ",
+            _ => "",
+        }
+    }
+
+    /// Screen column of the text side bar separator.
+    pub fn ascii_column(&self, bytes_per_line: usize) -> usize {
+        let separators: usize = (0..bytes_per_line)
+            .map(|column| self.separator(column, bytes_per_line).len())
+            .sum();
+        ADDRESS_WIDTH + bytes_per_line * self.radix.digits() + separators + 1
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::editor::Editor;
+
+// Macros played inside a macro, before giving up on a macro calling itself
+const MAX_DEPTH: usize = 20;
+
+// Names of the keys written between '<' and '>' in the macros file
+const KEY_NAMES: [(KeyCode, &str); 14] = [
+    (KeyCode::Esc, "ESC"),
+    (KeyCode::Enter, "ENTER"),
+    (KeyCode::Backspace, "BS"),
+    (KeyCode::Delete, "DEL"),
+    (KeyCode::Tab, "TAB"),
+    (KeyCode::Left, "LEFT"),
+    (KeyCode::Right, "RIGHT"),
+    (KeyCode::Up, "UP"),
+    (KeyCode::Down, "DOWN"),
+    (KeyCode::Home, "HOME"),
+    (KeyCode::End, "END"),
+    (KeyCode::PageUp, "PAGEUP"),
+    (KeyCode::PageDown, "PAGEDOWN"),
+    (KeyCode::Insert, "INSERT"),
+];
+
+/// Whether a macro can be recorded in the register `c`.
+pub fn is_register(c: char) -> bool {
+    c.is_ascii_lowercase()
+}
+
+// The macros are kept in the home directory, for all the files
+fn macros_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".rhexed_macros"))
+}
+
+/// Read the macros saved by the previous sessions, one `register:keys` per line.
+pub fn load() -> io::Result<HashMap<char, Vec<KeyEvent>>> {
+    let mut macros = HashMap::new();
+    let Some(path) = macros_path() else {
+        return Ok(macros);
+    };
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(macros),
+        Err(e) => return Err(e),
+    };
+    for line in text.lines() {
+        let mut chars = line.chars();
+        if let (Some(register), Some(':')) = (chars.next(), chars.next()) {
+            if let Some(keys) = decode_keys(chars.as_str()) {
+                macros.insert(register, keys);
+            }
+        }
+    }
+    Ok(macros)
+}
+
+/// Save a macro, keeping the others of the file which may have been
+/// recorded in another editor.
+pub fn store(register: char, keys: &[KeyEvent]) -> io::Result<()> {
+    let Some(path) = macros_path() else {
+        return Ok(());
+    };
+    let mut macros = load()?;
+    macros.insert(register, keys.to_vec());
+    let mut registers: Vec<&char> = macros.keys().collect();
+    registers.sort();
+    let mut text = String::new();
+    for register in registers {
+        let keys = encode_keys(&macros[register]).map_err(|code| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("the key {:?} can't be written in the macros file", code))
+        })?;
+        text.push_str(&format!("{}:{}\n", register, keys));
+    }
+    fs::write(path, text)
+}
+
+/// Keys as text, like `i00<ESC>j<C-r>`, or the first key which has no
+/// name to be written.
+pub fn encode_keys(keys: &[KeyEvent]) -> Result<String, KeyCode> {
+    let mut text = String::new();
+    for key in keys {
+        match key.code {
+            KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                text.push_str(&format!("<C-{}>", c));
+            }
+            KeyCode::Char('<') => text.push_str("<lt>"),
+            KeyCode::Char(c) => text.push(c),
+            code => {
+                let (_, name) = KEY_NAMES.iter().find(|(key_code, _)| *key_code == code).ok_or(code)?;
+                text.push_str(&format!("<{}>", name));
+            }
+        }
+    }
+    Ok(text)
+}
+
+fn decode_keys(text: &str) -> Option<Vec<KeyEvent>> {
+    let mut keys = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '<' {
+            keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+            continue;
+        }
+        let rest = chars.as_str();
+        let end = rest.find('>')?;
+        let name = &rest[..end];
+        let key = match name.strip_prefix("C-").and_then(|c| c.chars().next()) {
+            Some(c) => KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL),
+            None if name == "lt" => KeyEvent::new(KeyCode::Char('<'), KeyModifiers::NONE),
+            None => {
+                let (code, _) = KEY_NAMES.iter().find(|(_, key_name)| *key_name == name)?;
+                KeyEvent::new(*code, KeyModifiers::NONE)
+            }
+        };
+        keys.push(key);
+        chars = rest[end + 1..].chars();
+    }
+    Some(keys)
+}
+
+impl Editor {
+    /// Use the same macros as `other`, to play in a file a macro recorded
+    /// in another one.
+    pub fn share_macros(&mut self, other: &Editor) {
+        self.macros = Rc::clone(&other.macros);
+    }
+
+    /// Load the saved macros, when they are kept between sessions.
+    pub fn load_macros(&mut self) {
+        match load() {
+            Ok(macros) => *self.macros.borrow_mut() = macros,
+            Err(e) => self.show_error(format!("Cannot read the macros: {}", e)),
+        }
+    }
+
+    // The keys following `q{register}` are recorded until the next `q`
+    pub fn start_recording(&mut self, register: char) {
+        self.recording = Some(register);
+        self.macro_keys.clear();
+        self.refresh = true;
+    }
+
+    pub fn stop_recording(&mut self) {
+        let Some(register) = self.recording.take() else {
+            return;
+        };
+        // The 'q' which stopped the recording
+        self.macro_keys.pop();
+        let keys = mem::take(&mut self.macro_keys);
+        if self.keep_macros {
+            if let Err(e) = store(register, &keys) {
+                self.show_error(format!("Cannot save the macro: {}", e));
+            }
+        }
+        self.macros.borrow_mut().insert(register, keys);
+        self.refresh = true;
+    }
+
+    // Type the keys of a macro `count` times, '@' being the last played one
+    pub fn play_macro(&mut self, register: char, count: usize) {
+        let register = if register == '@' { self.last_macro } else { Some(register) };
+        let Some(keys) = register.and_then(|register| self.macros.borrow().get(&register).cloned()) else {
+            self.show_error(format!("No macro in register {}", register.unwrap_or('@')));
+            return;
+        };
+        if self.macro_depth == MAX_DEPTH {
+            self.show_error("Too many nested macros".to_string());
+            return;
+        }
+        self.last_macro = register;
+        self.macro_depth += 1;
+        // The keys typed to play the macro are not a change to repeat
+        self.change_keys.clear();
+        // Until an error, or a pass without any effect like at the end of
+        // the file
+        'passes: for _ in 0..count {
+            let state = (self.cursor_index, self.history.recorded());
+            for key in &keys {
+                self.replay_key(*key);
+                if self.has_error() {
+                    break 'passes;
+                }
+            }
+            if state == (self.cursor_index, self.history.recorded()) {
+                break;
+            }
+        }
+        self.macro_depth -= 1;
+        self.change_start = self.history.recorded();
+        self.refresh = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn keys_round_trip() {
+        let mut keys: Vec<KeyEvent> = "i00 <a>:w\\".chars().map(|c| key(KeyCode::Char(c))).collect();
+        keys.push(KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL));
+        keys.push(KeyEvent::new(KeyCode::Char('<'), KeyModifiers::CONTROL));
+        keys.extend(KEY_NAMES.iter().map(|(code, _)| key(*code)));
+
+        let text = encode_keys(&keys).unwrap();
+        assert!(!text.contains('\n'));
+        assert_eq!(decode_keys(&text), Some(keys));
+    }
+
+    #[test]
+    fn encoded_text() {
+        let keys = [
+            key(KeyCode::Char('i')),
+            key(KeyCode::Char('<')),
+            key(KeyCode::Esc),
+            KeyEvent::new(KeyCode::Char('r'), KeyModifiers::CONTROL),
+        ];
+        assert_eq!(encode_keys(&keys), Ok("i<lt><ESC><C-r>".to_string()));
+        assert_eq!(encode_keys(&[key(KeyCode::F(1))]), Err(KeyCode::F(1)));
+    }
+
+    #[test]
+    fn invalid_text() {
+        assert_eq!(decode_keys("a<FOO>"), None);
+        assert_eq!(decode_keys("a<ESC"), None);
+        assert_eq!(decode_keys(""), Some(Vec::new()));
+        assert_eq!(decode_keys("a>b").map(|keys| keys.len()), Some(3));
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crossterm::event::KeyCode;
+use crossterm::style::Stylize;
+use crate::editor::{Editor, Mode};
+use crate::editor::charset::{self, Glyph};
+use crate::editor::history::shift_offset;
+use crate::editor::screen::Frame;
+
+// Bytes shown after each mark in the list
+const PREVIEW_LENGTH: usize = 8;
+const LIST_WIDTH: usize = 74;
+
+/// Offsets named by a letter, following their bytes when bytes are inserted
+/// or deleted before them.
+#[derive(Default)]
+pub struct Marks {
+    offsets: BTreeMap<char, usize>,
+}
+
+impl Marks {
+    pub fn get(&self, name: char) -> Option<usize> {
+        self.offsets.get(&name).copied()
+    }
+
+    pub fn set(&mut self, name: char, offset: usize) {
+        self.offsets.insert(name, offset);
+    }
+
+    pub fn remove(&mut self, name: char) {
+        self.offsets.remove(&name);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.offsets.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (char, usize)> + '_ {
+        self.offsets.iter().map(|(name, offset)| (*name, *offset))
+    }
+
+    /// Whether a mark is set at `offset`.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.offsets.values().any(|mark| *mark == offset)
+    }
+
+    /// Follow a change of `old_length` bytes at `offset` into `new_length`
+    /// bytes.
+    pub fn shift(&mut self, offset: usize, old_length: usize, new_length: usize) {
+        for mark in self.offsets.values_mut() {
+            shift_offset(mark, offset, old_length, new_length);
+        }
+    }
+}
+
+pub fn is_mark_name(c: char) -> bool {
+    c.is_ascii_lowercase()
+}
+
+// The marks of all the files are kept in the home directory
+fn marks_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".rhexed_marks"))
+}
+
+// One line per file: `a=16,b=2048<TAB>/absolute/path`
+fn load_all() -> io::Result<Vec<(String, String)>> {
+    let Some(path) = marks_path() else {
+        return Ok(Vec::new());
+    };
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(marks, file)| (file.to_string(), marks.to_string()))
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+// The same file can be opened through different paths
+fn file_key(file_name: &str) -> String {
+    fs::canonicalize(file_name).map_or(file_name.to_string(), |path| path.to_string_lossy().into_owned())
+}
+
+/// Read the marks saved for a file.
+pub fn load(file_name: &str) -> io::Result<Marks> {
+    let key = file_key(file_name);
+    let mut marks = Marks::default();
+    if let Some((_, text)) = load_all()?.into_iter().find(|(file, _)| *file == key) {
+        for mark in text.split(',') {
+            let mut chars = mark.chars();
+            if let (Some(name), Some('=')) = (chars.next(), chars.next()) {
+                if let Ok(offset) = chars.as_str().parse() {
+                    marks.set(name, offset);
+                }
+            }
+        }
+    }
+    Ok(marks)
+}
+
+/// Save the marks of a file, keeping the ones of the other files.
+pub fn store(file_name: &str, marks: &Marks) -> io::Result<()> {
+    let Some(path) = marks_path() else {
+        return Ok(());
+    };
+    let key = file_key(file_name);
+    let mut files: Vec<(String, String)> = load_all()?.into_iter().filter(|(file, _)| *file != key).collect();
+    if !marks.is_empty() {
+        let text: Vec<String> = marks.iter().map(|(name, offset)| format!("{}={}", name, offset)).collect();
+        files.push((key, text.join(",")));
+    }
+    let text: String = files.iter().map(|(file, marks)| format!("{}\t{}\n", marks, file)).collect();
+    fs::write(path, text)
+}
+
+impl Editor {
+    pub fn set_mark(&mut self, name: char) {
+        self.marks.set(name, self.cursor_index);
+        self.save_marks_if_written();
+        self.refresh = true;
+    }
+
+    pub fn jump_to_mark(&mut self, name: char) {
+        match self.marks.get(name) {
+            Some(offset) if offset < self.buffer.len() => self.jump_to(offset),
+            Some(_) => self.show_error(format!("Mark {} is out of the file", name)),
+            None => self.show_error(format!("Mark {} is not set", name)),
+        }
+        self.refresh = true;
+    }
+
+    pub fn load_marks(&mut self) {
+        match load(&self.file_name) {
+            Ok(marks) => self.marks = marks,
+            Err(e) => self.show_error(format!("Cannot read the marks: {}", e)),
+        }
+    }
+
+    pub fn save_marks(&mut self) {
+        if let Err(e) = store(&self.file_name, &self.marks) {
+            self.show_error(format!("Cannot save the marks: {}", e));
+        }
+    }
+
+    // With unsaved changes, the marks have followed bytes which are not in
+    // the file yet: they are saved when the file is written
+    fn save_marks_if_written(&mut self) {
+        if !self.is_modified() {
+            self.save_marks();
+        }
+    }
+
+    pub fn marks_inputs(&mut self, key_code: KeyCode) {
+        let count = self.marks.iter().count();
+        let selected = self.marks.iter().nth(self.marks_row).map(|(name, _)| name);
+        match key_code {
+            KeyCode::Esc | KeyCode::Char('M') => {
+                self.mode = Mode::Normal;
+            }
+            KeyCode::Down => {
+                self.marks_row = (self.marks_row + 1).min(count.saturating_sub(1));
+            }
+            KeyCode::Up => {
+                self.marks_row = self.marks_row.saturating_sub(1);
+            }
+            KeyCode::Enter => {
+                if let Some(name) = selected {
+                    self.mode = Mode::Normal;
+                    self.jump_to_mark(name);
+                }
+            }
+            KeyCode::Delete | KeyCode::Backspace => {
+                if let Some(name) = selected {
+                    self.marks.remove(name);
+                    self.marks_row = self.marks_row.min(count.saturating_sub(2));
+                    self.save_marks_if_written();
+                }
+            }
+            KeyCode::Char(name) if is_mark_name(name) => {
+                self.mode = Mode::Normal;
+                self.jump_to_mark(name);
+            }
+            _ => {}
+        }
+        self.refresh = true;
+    }
+
+    /// Draw the list of the marks, over the bytes.
+    pub fn render_marks(&self, frame: &mut Frame, top: u16) {
+        let mut lines = vec![(format!("{:<width$}", "  Marks", width = LIST_WIDTH), false)];
+        if self.marks.is_empty() {
+            lines.push((format!("{:<width$}", "  No mark, m{a-z} sets one at the cursor", width = LIST_WIDTH), false));
+        }
+        for (row, (name, offset)) in self.marks.iter().enumerate() {
+            let end = (offset + PREVIEW_LENGTH).min(self.buffer.len());
+            let bytes = if offset < end { self.buffer.slice(offset, end) } else { Vec::new() };
+            let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            let text: String = self.charset.glyphs(&bytes, offset)
+                .into_iter()
+                .map(|glyph| match glyph {
+                    Glyph::Char(c) if !charset::is_wide(c) => c,
+                    Glyph::Continuation => ' ',
+                    _ => '.',
+                })
+                .collect();
+            let line = format!("  {}   {:08x}   {:<24}  {}", name, offset, hex.join(" "), text);
+            lines.push((format!("{:<width$}", line, width = LIST_WIDTH), row == self.marks_row));
+        }
+        lines.push((format!("{:<width$}", "", width = LIST_WIDTH), false));
+        lines.push((
+            format!("{:<width$}", "  {a-z} or <ENTER>: jump   <DEL>: remove   <ESC>: quit", width = LIST_WIDTH),
+            false,
+        ));
+
+        for (i, (line, selected)) in lines.into_iter().enumerate() {
+            frame.move_to(5, top + i as u16);
+            if selected {
+                frame.print(line.black().on_white());
+            } else {
+                frame.print(line.white().on_black());
+            }
+        }
+    }
+}
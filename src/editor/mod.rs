@@ -1,29 +1,67 @@
-use std::io::{self, stdout, Read, Stdout, Write};
-use std::fs::File;
-use std::{cmp, usize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::cmp;
+use std::mem;
+use std::rc::Rc;
 
 use crossterm::event::{KeyCode, KeyEvent};
-use crossterm::style::{Color, SetColors};
+use crossterm::style::Color;
 use crossterm::terminal;
-use crossterm::{
-    cursor,
-    queue,
-    style::{
-        Color::{DarkGrey, DarkYellow, Magenta, Red, Reset, DarkGreen},
-        Colors, Print, PrintStyledContent, Stylize,
-    },
-    terminal::{Clear, ClearType},
-    QueueableCommand,
+use crossterm::style::{
+    Color::{Black, DarkCyan, DarkGrey, DarkYellow, Magenta, Red, Reset, DarkGreen},
+    Stylize,
 };
 
 
 mod normal_mode;
 mod edit_mode;
 mod jump_mode;
+mod jump_list;
+mod pointer;
 mod selection_mode;
 mod search_mode;
+mod text_search_mode;
+mod pattern;
+mod searcher;
+mod replace_mode;
+mod number;
+mod number_search_mode;
+mod inspector;
+mod macros;
+mod marks;
+mod registers;
+mod layout;
+mod radix;
+mod charset;
+mod screen;
+mod command;
+mod command_mode;
+mod expression;
 mod edit_ascii_mode;
 mod help_mode;
+mod history;
+mod buffer;
+mod paged_file;
+mod save;
+mod quit_mode;
+mod error;
+
+use history::{Change, History};
+use buffer::Buffer;
+use save::save;
+pub use error::EditorError;
+pub use screen::Screen;
+use text_search_mode::TextEncoding;
+use pattern::Pattern;
+use searcher::SearchJob;
+use replace_mode::ReplaceState;
+use number::{Endianness, NumberType};
+use layout::Layout;
+use charset::{Charset, Glyph};
+use marks::Marks;
+use registers::Registers;
+use jump_list::JumpList;
 
 const RHEXED: [&str; 6] = [
     "d8888b. db   db d88888b db    db d88888b d8888b.",
@@ -34,7 +72,7 @@ const RHEXED: [&str; 6] = [
     "88   YD YP   YP Y88888P YP    YP Y88888P Y8888D'",
 ];
 
-const HELP: [&str; 26] = [
+const HELP: [&str; 57] = [
 "      - hjkl or arrow     move                                            ",
 "      - g                 move to the beginning of the file               ",
 "      - G                 move to the end of the file                     ",
@@ -46,32 +84,77 @@ const HELP: [&str; 26] = [
 "      - b                 go to the previous page                         ",
 "      - N                 go to the next file                             ",
 "      - B                 go to the previous file                         ",
-"      - J                 go to a specified address                       ",
+"      - J                 go to an address (0x10, 4096, +16, end-8, 'a*2) ",
+"      - <CTRL-o>          go back to where the cursor was before a jump   ",
+"      - <CTRL-i>, <TAB>   go forward again after <CTRL-o>                 ",
+"      - f                 follow the pointer at the cursor (:set pointer=)",
+"      - <CTRL-t>          go back to where the last pointer was followed  ",
+"      - :                 command line (:w, :q, :goto, :set, :fill, ...)  ",
+"      - /                 search a text (CTRL-t: case, CTRL-e: encoding)  ",
+"      - S                 replace (find/replacement/flags, g: all, c: ask)",
+"      - #                 search a number (value type [le|be])            ",
+"      - D                 show / hide the data inspector                  ",
+"      - =                 edit a value in the inspector (jk, hl, <ENTER>) ",
+"      - W                 bytes per line: 8, 16, 24, 32 or terminal width ",
+"      - C                 group bytes by 1, 2, 4 or 8                     ",
+"      - |                 show / hide a gap every 8 bytes                 ",
+"      - F                 show bytes in hex, binary, octal or decimal     ",
+"      - E                 text encoding (ascii, latin-1, cp437, ebcdic...)",
 "      - a                 insert a byte at cursor position                ",
 "      - x                 cut a byte                                      ",
 "      - y                 copy a byte or a range of selected bytes        ",
-"      - p                 paste a byte or a range of selected bytes       ",
-"      - i                 insert mode                                     ",
+"      - p                 paste over the bytes at the cursor              ",
+"      - P                 paste, inserted at the cursor                   ",
+"      - \"{a-z}            register of the next y, x or p (\"0-\"9: history) ",
+"      - v                 select bytes, with the moves, pages, g, G and J ",
+"        then y, d, p      copy, cut or paste over the selection           ",
+"        then f, o         fill the selection, go to its other end         ",
+"      - u                 undo the last change                            ",
+"      - <CTRL-r>          redo the last undone change                     ",
+"      - .                 repeat the last change at the cursor            ",
+"      - 0-9               count before a command, e.g. 10j, 4x or 3p      ",
+"      - q{a-z}            record a macro, until q is typed again          ",
+"      - @{a-z}            play a macro (@@: the last one again)           ",
+"      - m{a-z}            set a mark at the cursor                        ",
+"      - '{a-z}            go to a mark                                    ",
+"      - M                 list the marks                                  ",
+"      - i                 insert mode: the typed bytes are inserted       ",
 "      - I                 insert mode (in ascii)                          ",
+"      - R                 overwrite mode: the typed bytes replace the ones",
+"      - <INSERT>          switch between insert and overwrite             ",
+"      - <BACKSPACE>       delete the previous byte, when inserting        ",
 "      - <ESC>             quit insert mode                                ",
-"      - <TAB>             show / hide title                               ",
+"      - T                 show / hide title                               ",
+"      - <CTRL-l>          redraw the screen                               ",
 "      - r                 reload file                                     ",
 "      - w                 write file                                      ",
-"      - q                 quit                                            ",
+"      - Q                 quit                                            ",
 "      - ?                 help                                            ",
-"                                                                          ",
-"                        <ESC> or '?' to quit help                         "
 ];
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, PartialEq)]
 pub enum Mode {
     Normal,
     Search,
+    TextSearch,
+    NumberSearch,
+    Replace,
+    ReplaceConfirm,
+    Inspector,
+    InspectorEdit,
+    Command,
     Edit,
     AsciiEdit,
     Selection,
     Jump,
-    Help
+    Marks,
+    Help,
+    Quit
+}
+
+enum Message {
+    Info(String),
+    Error(String),
 }
 
 struct ColorProfile {
@@ -86,53 +169,196 @@ pub struct Editor {
     pub id: usize,
     pub refresh: bool,
     pub exit: bool,
+    pub force_exit: bool,
+    pub backup: bool,
+    // Save the macros for the next sessions
+    pub keep_macros: bool,
     mode: Mode,
     cursor_index: usize,
     cursor_start: usize,
     nibble_index:u8,
+    // Whether the edit modes insert the typed bytes, instead of overwriting
+    insert: bool,
     page: usize,
-    clipboard: Vec<u8>,
-    search_pattern: Vec<u8>,
-    search_result: Vec<u32>,
-    buffer: Vec<u8>,
-    jump_adress: u32,
+    // Shared by the editors of all the files
+    registers: Rc<RefCell<Registers>>,
+    // Register chosen with '"' for the next copy, cut or paste
+    register: Option<char>,
+    search_pattern: Pattern,
+    search_result: Vec<usize>,
+    search_job: Option<SearchJob>,
+    replace_range: Option<(usize, usize)>,
+    replace_state: Option<ReplaceState>,
+    input: String,
+    search_text: String,
+    search_ignore_case: bool,
+    search_encoding: TextEncoding,
+    show_inspector: bool,
+    inspector_row: usize,
+    inspector_endianness: Endianness,
+    layout: Layout,
+    bytes_per_line: usize,
+    charset: Charset,
+    command_history: Vec<String>,
+    command_history_index: usize,
+    completions: Vec<String>,
+    completion_index: usize,
+    // The selection the command applies to
+    command_range: Option<(usize, usize)>,
+    // Count typed before a normal mode command
+    count: Option<usize>,
+    // Keys of the command being typed, and of the last one which changed
+    // the buffer, repeated by '.'
+    change_keys: Vec<KeyEvent>,
+    change_start: usize,
+    last_change: Vec<KeyEvent>,
+    replaying: bool,
+    // A key waiting for the register it applies to, like the 'q' of 'qa'
+    pending: Option<char>,
+    // Shared by the editors of all the files, like the registers
+    macros: Rc<RefCell<HashMap<char, Vec<KeyEvent>>>>,
+    recording: Option<char>,
+    macro_keys: Vec<KeyEvent>,
+    last_macro: Option<char>,
+    macro_depth: usize,
+    marks: Marks,
+    marks_row: usize,
+    // Help page shown, and the number of lines the terminal leaves for it
+    help_page: usize,
+    help_rows: usize,
+    jumps: JumpList,
+    // Mode the jump prompt goes back to, to keep a selection going
+    jump_return: Mode,
+    // How the pointers followed with 'f' are read
+    pointer_type: NumberType,
+    pointer_endianness: Endianness,
+    pointer_base: i128,
+    buffer: Buffer,
+    history: History,
     file_name: String,
     terminal_height: usize,
-    page_size: usize
+    page_size: usize,
+    unsaved_files: usize,
+    message: Option<Message>
 
 }
 
 impl Editor {
-    pub fn new(file_name: &String, id: usize) -> Editor {
-        let mut f = File::open(file_name).unwrap();
-        let terminal_height = terminal::size().unwrap().1 as usize;
+    pub fn new(file_name: &str, id: usize) -> Result<Editor, EditorError> {
+        let buffer = Buffer::open(file_name).map_err(|e| EditorError::Open(file_name.to_string(), e))?;
+        let terminal_height = terminal::size().map_err(EditorError::Terminal)?.1 as usize;
 
-        let mut buf: Vec<u8> = Vec::new();
-        f.read_to_end(&mut buf).unwrap();
-        Editor{
+        let mut editor = Editor{
             id,
             refresh: true,
             exit: false,
+            force_exit: false,
+            backup: false,
+            keep_macros: false,
             mode: Mode::Normal,
             cursor_index: 0,
             cursor_start: 0,
             nibble_index: 0,
+            insert: true,
             page: 0,
-            clipboard: Vec::new(),
-            search_pattern: Vec::new(),
+            registers: Rc::default(),
+            register: None,
+            search_pattern: Pattern::default(),
             search_result: Vec::new(),
-            buffer: buf,
-            jump_adress: 0,
-            file_name: file_name.clone(),
+            search_job: None,
+            replace_range: None,
+            replace_state: None,
+            input: String::new(),
+            search_text: String::new(),
+            search_ignore_case: false,
+            search_encoding: TextEncoding::Utf8,
+            show_inspector: false,
+            inspector_row: 0,
+            inspector_endianness: Endianness::Little,
+            layout: Layout::default(),
+            bytes_per_line: 16,
+            charset: Charset::Latin1,
+            command_history: Vec::new(),
+            command_history_index: 0,
+            completions: Vec::new(),
+            completion_index: 0,
+            command_range: None,
+            count: None,
+            change_keys: Vec::new(),
+            change_start: 0,
+            last_change: Vec::new(),
+            replaying: false,
+            pending: None,
+            macros: Rc::default(),
+            recording: None,
+            macro_keys: Vec::new(),
+            last_macro: None,
+            macro_depth: 0,
+            marks: Marks::default(),
+            marks_row: 0,
+            help_page: 0,
+            help_rows: HELP.len(),
+            jumps: JumpList::default(),
+            jump_return: Mode::Normal,
+            pointer_type: NumberType::U32,
+            pointer_endianness: Endianness::Little,
+            pointer_base: 0,
+            buffer,
+            history: History::new(),
+            file_name: file_name.to_string(),
             terminal_height,
-            page_size: (terminal_height - 12) * 16,
+            page_size: cmp::max(terminal_height.saturating_sub(12), 1) * 16,
+            unsaved_files: 0,
+            message: None,
+        };
+        editor.load_marks();
+        Ok(editor)
+    }
+
+    /// Show an error in the header, until the next key is pressed.
+    pub fn show_error(&mut self, message: String) {
+        self.message = Some(Message::Error(message));
+        self.refresh = true;
+    }
+
+    /// Show an information in the header, until the next key is pressed.
+    pub fn show_info(&mut self, message: String) {
+        self.message = Some(Message::Info(message));
+        self.refresh = true;
+    }
+
+    /// Type again a key of a macro or of a repeated change. A search it
+    /// starts is done before the next key, which would cancel it otherwise.
+    pub fn replay_key(&mut self, key_event: KeyEvent) {
+        self.update(key_event);
+        while self.is_searching() {
+            self.search_step();
         }
     }
 
     pub fn update(&mut self, key_event: KeyEvent) {
+        if self.message.take().is_some() {
+            self.refresh = true;
+        }
+        // While searching, only a cancellation is possible
+        if self.is_searching() {
+            if key_event.code == KeyCode::Esc {
+                self.cancel_search();
+            }
+            return;
+        }
+        if !self.replaying {
+            if self.mode == Mode::Normal && self.count.is_none() && self.change_keys.is_empty() {
+                self.change_start = self.history.recorded();
+            }
+            self.change_keys.push(key_event);
+        }
+        if self.recording.is_some() && self.macro_depth == 0 && !self.replaying {
+            self.macro_keys.push(key_event);
+        }
         match self.mode {
             Mode::Normal =>{
-                self.normal_inputs(key_event.code);
+                self.normal_inputs(key_event);
             },
             Mode::Edit => {
                 self.edit_inputs(key_event.code);
@@ -147,33 +373,92 @@ impl Editor {
             Mode::Search => {
                 self.search_inputs(key_event.code);
             }
+            Mode::TextSearch => {
+                self.text_search_inputs(key_event);
+            }
+            Mode::NumberSearch => {
+                self.number_search_inputs(key_event.code);
+            }
+            Mode::Replace => {
+                self.replace_inputs(key_event.code);
+            }
+            Mode::ReplaceConfirm => {
+                self.replace_confirm_inputs(key_event.code);
+            }
+            Mode::Inspector => {
+                self.inspector_inputs(key_event.code);
+            }
+            Mode::InspectorEdit => {
+                self.inspector_edit_inputs(key_event.code);
+            }
+            Mode::Command => {
+                self.command_inputs(key_event.code);
+            }
             Mode::AsciiEdit => {
                 self.edit_ascii_input(key_event.code);
             }
+            Mode::Marks => {
+                self.marks_inputs(key_event.code);
+            }
             Mode::Help => {
                 self.help_inputs(key_event.code);
             }
+            Mode::Quit => {
+                self.quit_inputs(key_event.code);
+            }
+        }
+
+        // A command is over when back to normal mode
+        if !self.replaying
+            && self.mode == Mode::Normal
+            && self.count.is_none()
+            && self.pending.is_none()
+            && self.register.is_none()
+        {
+            let keys = mem::take(&mut self.change_keys);
+            if self.history.recorded() > self.change_start {
+                self.last_change = keys;
+            }
         }
 
-        self.cursor_index = cmp::max(0, self.cursor_index);
-        self.cursor_index = cmp::min(self.cursor_index, self.buffer.len() - 1);
+        self.cursor_index = cmp::min(self.cursor_index, self.last_position());
+        self.check_read_error();
 
     }
 
-    pub fn render(&mut self, stdout: &mut Stdout, show_title: bool) -> io::Result<()> {
-        self.terminal_height = terminal::size()?.1 as usize;
+    pub fn render(&mut self, screen: &mut Screen, show_title: bool) -> io::Result<()> {
+        let (terminal_width, terminal_height) = terminal::size()?;
+        self.terminal_height = terminal_height as usize;
+        let mut grid_width = terminal_width as usize;
+        if self.show_inspector {
+            grid_width = grid_width.saturating_sub(inspector::INSPECTOR_WIDTH);
+        }
+        self.bytes_per_line = self.layout.bytes_per_line(grid_width);
         if show_title && self.terminal_height > 20 {
-            self.page_size = (self.terminal_height - 12) * 16;
+            self.page_size = (self.terminal_height - 12) * self.bytes_per_line;
         } else {
-            self.page_size = (self.terminal_height - 6) * 16;
+            self.page_size = cmp::max(self.terminal_height.saturating_sub(6), 1) * self.bytes_per_line;
 
         }
         if self.cursor_index >= (self.page + 1) * self.page_size || self.cursor_index < self.page * self.page_size {
             self.page = self.cursor_index / self.page_size;
         }
         self.refresh = false;
+
+        // Only the displayed page is read from the file
+        let page_start = self.page * self.page_size;
+        let limit: usize = cmp::min(self.buffer.len(), (self.page + 1) * self.page_size);
+        let page_bytes = self.buffer.slice(page_start, limit);
+
+        // A multibyte character can start a few bytes before the page
+        let glyphs_start = page_start.saturating_sub(3);
+        let glyphs_end = cmp::min(self.buffer.len(), limit + 3);
+        let glyphs = self.charset.glyphs(&self.buffer.slice(glyphs_start, glyphs_end), glyphs_start);
+        let glyphs = &glyphs[page_start - glyphs_start..];
+        self.check_read_error();
+
         let color_profile = match self.mode {
-            Mode::Normal | Mode::Search | Mode::Help=> {
+            Mode::Normal | Mode::Search | Mode::TextSearch | Mode::NumberSearch | Mode::Replace | Mode::ReplaceConfirm | Mode::Command | Mode::Marks | Mode::Help | Mode::Quit => {
                 ColorProfile {
                     ascii_fg: DarkYellow,
                     cursor_fg: DarkGrey,
@@ -182,7 +467,7 @@ impl Editor {
                     selection_bg: DarkYellow
                 }
             },
-            Mode::Edit | Mode::AsciiEdit => {
+            Mode::Edit | Mode::AsciiEdit | Mode::Inspector | Mode::InspectorEdit => {
                 ColorProfile {
                     ascii_fg: DarkYellow,
                     cursor_fg: DarkGrey,
@@ -210,97 +495,158 @@ impl Editor {
                 }
             }
         };
-        stdout.queue(Clear(ClearType::All))?
-        .queue(cursor::MoveTo(0,0))?;
+        let mut frame = screen.frame()?;
 
         let mut line: u16 = 0;
         if show_title && self.terminal_height > 20 {
             for line_text in RHEXED.iter() {
-                queue!(
-                    stdout,
-                    cursor::MoveTo(0, line),
-                    PrintStyledContent(line_text.magenta()),
-                )?;
+                frame.move_to(0, line);
+                frame.print(line_text.magenta());
                 line += 1;
             }
-            queue!(
-                stdout,
-                cursor::MoveToNextLine(2))?;
+            frame.move_to_next_line(2);
         }
 
         match self.mode {
             Mode::Edit => {
-                queue!(
-                    stdout,
-                    cursor::MoveToColumn(30),
-                    PrintStyledContent("-- EDIT --".magenta()),
-                )?;
+                let name = if self.insert { "INSERT" } else { "EDIT" };
+                frame.move_to_column(30);
+                frame.print(format!("-- {} --", name).magenta());
+                frame.print(format!("  [{}, <INSERT>: insert / overwrite]", self.layout.radix.name()).green());
             }
             Mode::AsciiEdit => {
-                queue!(
-                    stdout,
-                    cursor::MoveToColumn(27),
-                    PrintStyledContent("-- ASCII EDIT --".magenta()),
-                )?;
+                let name = if self.insert { "ASCII INSERT" } else { "ASCII EDIT" };
+                frame.move_to_column(27);
+                frame.print(format!("-- {} --", name).magenta());
+                frame.print(format!("  [{}, <INSERT>: insert / overwrite]", self.charset.name()).green());
             }
             Mode::Jump => {
-                queue!(
-                    stdout,
-                    cursor::MoveToColumn(20),
-                    PrintStyledContent("Jump to ".magenta()),
-                    PrintStyledContent(format!("0x{:08x}", self.jump_adress).magenta())
-                    )?;
+                frame.move_to_column(20);
+                frame.print(format!("Jump to {}_", self.input).magenta());
+                match self.evaluate_address(&self.input) {
+                    Ok(address) => frame.print(format!("  = {:08x}", address).green()),
+                    Err(_) => frame.print("  [0x10, 4096, +16, end-0x10, 'a*2, cur+(8*3)]".green()),
                 }
+            }
             Mode::Search => {
-                let mut searching_pattern: String = "".to_string();
-                for s in self.search_pattern.iter_mut() {
-                    searching_pattern = format!("{} {:02x}", searching_pattern, s);
+                frame.move_to_column(20);
+                frame.print(format!("Search {}", self.search_pattern).magenta());
+                if let Some(Message::Error(text)) = &self.message {
+                    frame.print(format!("  {}", text).red());
+                }
+            }
+            Mode::TextSearch => {
+                let case = if self.search_ignore_case { "ignore case" } else { "match case" };
+                frame.move_to_column(20);
+                frame.print(format!("Search \"{}\"", self.search_text).magenta());
+                frame.print(format!("  [{}, {}]", self.search_encoding.name(), case).green());
+            }
+            Mode::NumberSearch => {
+                frame.move_to_column(20);
+                frame.print(format!("Search number {}", self.input).magenta());
+                frame.print("  [value u8..u64|i8..i64|f32|f64 (le|be)]".green());
+            }
+            Mode::Replace => {
+                frame.move_to_column(20);
+                frame.print(format!("Replace {}", self.input).magenta());
+                frame.print("  [find/replacement/flags]".green());
+            }
+            Mode::ReplaceConfirm => {
+                frame.move_to_column(20);
+                frame.print("Replace ? (y)es (n)o (a)ll (q)uit".magenta());
+            }
+            Mode::Inspector => {
+                frame.move_to_column(20);
+                frame.print("-- INSPECTOR --".magenta());
+                frame.print("  [jk: type, hl: endianness, <ENTER>: edit]".green());
+            }
+            Mode::InspectorEdit => {
+                frame.move_to_column(20);
+                frame.print(format!("New {} value {}", self.inspector_type().name(), self.input).magenta());
+            }
+            Mode::Command => {
+                frame.move_to_column(0);
+                frame.print(format!(":{}_", self.input).magenta());
+                // The last completion is what was typed
+                if self.completions.len() > 1 {
+                    let names: Vec<&str> = self.completions[..self.completions.len() - 1].iter()
+                        .filter_map(|line| line.rsplit(' ').next())
+                        .collect();
+                    frame.print(format!("  [{}]", names.join(" ")).green());
+                }
+            }
+            Mode::Quit => {
+                frame.move_to_column(10);
+                frame.print(format!("{} file(s) not saved, quit anyway ? (y/n)", self.unsaved_files).red());
+            }
+            _ if self.is_searching() => {
+                frame.move_to_column(20);
+                frame.print(format!("Searching... {}%  (<ESC> to cancel)", self.search_progress()).magenta());
+            }
+            _ if self.count.is_some() || self.pending.is_some() || self.register.is_some() => {
+                let register = self.register.map(|name| format!("\"{}", name)).unwrap_or_default();
+                let count = self.count.map(|count| count.to_string()).unwrap_or_default();
+                frame.move_to_column(20);
+                frame.print(format!("{}{}{}", register, count, self.pending.map(String::from).unwrap_or_default()).magenta());
+            }
+            _ if self.message.is_none() && self.recording.is_some() => {
+                frame.move_to_column(20);
+                frame.print(format!("recording @{}", self.recording.unwrap_or(' ')).magenta());
+            }
+            _ => {
+                let message = match &self.message {
+                    Some(Message::Info(text)) => Some(text.to_string().green()),
+                    Some(Message::Error(text)) => Some(text.to_string().red()),
+                    None => None,
+                };
+                if let Some(message) = message {
+                    frame.move_to_column(0);
+                    frame.print(message);
                 }
-                queue!(
-                    stdout,
-                    cursor::MoveToColumn(20),
-                    PrintStyledContent(format!("Search {}", searching_pattern).magenta())
-                )?;
-
             }
-            _ => {}
         }
 
         // Header info data
-        queue!(
-            stdout,
-            cursor::MoveToNextLine(1),
-            PrintStyledContent(format!("File {}: ", self.id).green()),
-            PrintStyledContent(format!("{}", self.file_name).magenta()))?;
+        frame.move_to_next_line(1);
+        frame.print(format!("File {}: ", self.id).green());
+        frame.print(self.file_name.to_string().magenta());
+
+        if self.is_modified() {
+            frame.print(" [+]".red());
+        }
 
         if !self.search_pattern.is_empty() {
-            queue!(
-                stdout,
-                PrintStyledContent("    Search result : ".green()),
-                PrintStyledContent(format!("{}", self.search_result.len()).magenta()))?;
+            frame.print("    Search result : ".green());
+            frame.print(format!("{}", self.search_result.len()).magenta());
+        }
+        frame.move_to_next_line(1);
+        frame.print("Size : ".green());
+        frame.print(format!("{} bytes", self.buffer.len()).magenta());
+        frame.print("  -  Page : ".green());
+        frame.print(format!("{} / {}", self.page + 1 , self.buffer.len() / self.page_size + 1).magenta());
+        frame.print("  -  Address : ".green());
+        frame.print(format!("{:08x}", self.cursor_index).magenta());
+        frame.print(" / ".green());
+        frame.print(format!("{:08x}", self.buffer.len()).magenta());
+        frame.move_to_next_line(1);
+
+        if self.show_inspector {
+            let grid_top = if show_title && self.terminal_height > 20 { 10 } else { 3 };
+            let column = self.layout.ascii_column(self.bytes_per_line) + 3 + self.bytes_per_line + 3;
+            self.render_inspector(&mut frame, column as u16, grid_top);
+            frame.move_to(0, grid_top);
         }
-        queue!(
-            stdout,
-            cursor::MoveToNextLine(1),
-            PrintStyledContent("Size : ".green()),
-            PrintStyledContent(format!("{} bytes", self.buffer.len()).magenta()),
-            PrintStyledContent("  -  Page : ".green()),
-            PrintStyledContent(format!("{} / {}", self.page + 1 , self.buffer.len() / self.page_size + 1).magenta()),
-            PrintStyledContent("  -  Address : ".green()),
-            PrintStyledContent(format!("{:08x}", self.cursor_index).magenta()),
-            PrintStyledContent(" / ".green()),
-            PrintStyledContent(format!("{:08x}", self.buffer.len()).magenta()),
-            cursor::MoveToNextLine(1)
-
-            )?;
 
-        let limit: usize = cmp::min(self.buffer.len(), (self.page + 1) * self.page_size);
-        for i in (self.page * self.page_size)..limit {
+        // When inserting, the cursor can be after the last byte
+        let end = if self.cursor_index >= self.buffer.len() { limit + 1 } else { limit };
+        let highlights = self.highlight_map(page_start, end);
+        for i in page_start..end {
+            let glyph = glyphs.get(i - page_start).copied().unwrap_or(Glyph::NotPrintable);
 
             // Start address display
-            if i % 16 == 0 {
-                stdout.queue(SetColors(Colors::new(Reset, Reset)))?;
-                stdout.queue(PrintStyledContent(format!("{:08x} : ", i).green()))?;
+            let column = i % self.bytes_per_line;
+            if column == 0 {
+                frame.print(format!("{:08x} : ", i).green());
             }
 
             // Line of hex data display
@@ -313,131 +659,230 @@ impl Editor {
             if i == self.cursor_index {
                 fg_color = color_profile.cursor_fg;
                 bg_color = color_profile.cursor_bg;
-            } else if is_printable_code(self.buffer[i]) {
-                fg_color = color_profile.ascii_fg;
-            } else if self.mode == Mode::Selection && i >= self.cursor_start && i <= self.cursor_index {
+            } else if self.is_selected(i) {
                 fg_color = color_profile.selection_fg;
                 bg_color = color_profile.selection_bg;
-            } else if self.mode == Mode::Edit && is_printable_code(self.buffer[i]) {
+            } else if glyph != Glyph::NotPrintable {
+                fg_color = color_profile.ascii_fg;
+            } else if self.mode == Mode::Edit && glyph != Glyph::NotPrintable {
                 fg_color = DarkGreen;
             }
 
-            // Show seaarch result with background highlight
-            // All the pattenr will be highlighted
-            if self.search_pattern.len() != 0  && self.search_result.len() != 0 {
-                // Affichage des résultat de recherche
-                for s in 0..self.search_pattern.len() {
-                    match self.search_result.iter().find(|res| **res + s as u32 == i as u32) {
-                        Some(_) => {
-                            fg_color = color_profile.selection_fg;
-                            bg_color = color_profile.selection_bg;
-                            if self.cursor_index == i {bg_color = color_profile.cursor_bg;}
-                        }
-                        None => {}
-                    }
-                }
+            // Marked bytes
+            if i != self.cursor_index && self.marks.contains(i) {
+                fg_color = Black;
+                bg_color = DarkCyan;
             }
 
-            stdout.queue(SetColors(Colors::new(fg_color, bg_color)))?;
-
-            // match self.search_result.iter().find(|c| (**c) as usize == i) {
-            //     Some(_) => {
-            //         stdout.queue(SetColors(Colors::new(
-            //                 color_profile.selection_fg,
-            //                 color_profile.selection_bg)))?;
-            //     }
-            //     None => {}
-            // }
+            // Search results and the occurrence waiting for a replacement
+            // confirmation are highlighted
+            if highlights[i - page_start] {
+                fg_color = color_profile.selection_fg;
+                bg_color = if i == self.cursor_index { color_profile.cursor_bg } else { color_profile.selection_bg };
+            }
 
             // Then, hex code display
-            stdout.queue(Print(format!("{:02x}", self.buffer[i])))?
-                .queue(SetColors(Colors::new(Reset, Reset)))?
-                .queue(Print(" "))?;
+            let cell = match page_bytes.get(i - page_start) {
+                Some(byte) => self.layout.radix.format(*byte),
+                None => " ".repeat(self.layout.radix.digits()),
+            };
+            if i == self.cursor_index && self.mode == Mode::Edit {
+                // The digit being typed is underlined
+                let (before, after) = cell.split_at(self.nibble_index as usize);
+                let (digit, after) = after.split_at(1);
+                frame.print(before.with(fg_color).on(bg_color));
+                frame.print(digit.with(fg_color).on(bg_color).underlined());
+                frame.print(after.with(fg_color).on(bg_color));
+            } else {
+                frame.print(cell.with(fg_color).on(bg_color));
+            }
+            frame.print(self.layout.separator(column, self.bytes_per_line).reset());
 
 
-            //  And, at the end of th 16 bytes line,  Char Side bar display
-            if i % 16 == 15 || i == self.buffer.len() - 1 {
+            //  And, at the end of the line,  Char Side bar display
+            if column == self.bytes_per_line - 1 || i == end - 1 {
 
 
                 // Separator
-                stdout.queue(cursor::MoveToColumn(60))?
-                    .queue(PrintStyledContent("|  ".green()))?;
+                frame.move_to_column(self.layout.ascii_column(self.bytes_per_line) as u16);
+                frame.print("|  ".green());
                 
-                for c in 0..16 {
+                // A wide char takes the place of the next one
+                let mut wide = false;
+                for c in 0..self.bytes_per_line {
 
                     // Index of char to display
-                    let char_index = (i / 16) * 16 + c;
+                    let char_index = (i / self.bytes_per_line) * self.bytes_per_line + c;
 
                     //Set char if printable or '.' dot if not
                     if char_index < self.buffer.len() {
-                        let displayed_char = if is_printable_code(self.buffer[char_index]) {
-                            self.buffer[char_index] as char
-                        } else {
-                            '.'
+                        let glyph = glyphs[char_index - page_start];
+                        let displayed_char = match glyph {
+                            Glyph::Char(c) => c,
+                            Glyph::Continuation if wide => {
+                                wide = false;
+                                continue;
+                            }
+                            Glyph::Continuation => ' ',
+                            Glyph::NotPrintable => '.',
                         };
-
-                        // Set Char color
-                        if char_index == self.cursor_index {
-                            stdout.queue(SetColors(Colors::new(
-                                        color_profile.cursor_fg,
-                                        color_profile.cursor_bg)))?;
-                        } else if is_printable_code(self.buffer[char_index]) {
-                            stdout.queue(SetColors(Colors::new(
-                                        DarkYellow,
-                                        Reset)))?;
+                        wide = matches!(glyph, Glyph::Char(c) if charset::is_wide(c));
+
+                        // Char color
+                        let styled = if char_index == self.cursor_index {
+                            displayed_char.with(color_profile.cursor_fg).on(color_profile.cursor_bg)
+                        } else if self.is_selected(char_index) {
+                            displayed_char.with(color_profile.selection_fg).on(color_profile.selection_bg)
+                        } else if glyph != Glyph::NotPrintable {
+                            displayed_char.with(DarkYellow)
                         } else {
-                            stdout.queue(SetColors(Colors::new(Reset, Reset)))?;
-                        }
-
-                        // Queue the char
-
-                        stdout.queue(Print(format!("{}", displayed_char)))?;
-                        // Reset Colors
-                        // stdout.queue(SetColors(Colors::new(Reset, Reset)))?;
+                            displayed_char.reset()
+                        };
+                        frame.print(styled);
+                    } else if char_index == self.cursor_index {
+                        frame.print(' '.with(color_profile.cursor_fg).on(color_profile.cursor_bg));
                     }
                 }
-                stdout.queue(cursor::MoveToNextLine(1))?;
+                frame.move_to_next_line(1);
             }
         } 
 
+        if self.mode == Mode::Marks {
+            let top = if show_title && self.terminal_height > 20 { 5 } else { 3 };
+            self.render_marks(&mut frame, top);
+        }
+
         if self.mode == Mode::Help {
-            let mut line: u16 = 0;
-            if show_title && self.terminal_height > 20 {
-                for line_text in HELP.iter() {
-                    queue!(
-                        stdout,
-                        cursor::MoveTo(5, line + 5),
-                        PrintStyledContent(line_text.white()),
-                    )?;
-                    line += 1;
-                }
-                queue!(
-                    stdout,
-                    cursor::MoveToNextLine(2))?;
+            let top = if show_title && self.terminal_height > 20 { 5 } else { 3 };
+            // Room for the blank line and the footer
+            self.help_rows = cmp::max(self.terminal_height.saturating_sub(top + 3), 1);
+            self.render_help(&mut frame, top as u16);
+        }
+
+        screen.show(frame)
+    }
+
+    // Whether each byte of the page is part of a search result, or of the
+    // occurrence waiting for a replacement confirmation
+    fn highlight_map(&self, page_start: usize, limit: usize) -> Vec<bool> {
+        let mut highlights = vec![false; limit - page_start];
+        let mut mark = |start: usize, end: usize| {
+            for i in cmp::max(start, page_start)..cmp::min(end, limit) {
+                highlights[i - page_start] = true;
+            }
+        };
+        let length = self.search_pattern.len();
+        if length > 0 {
+            for result in results_between(&self.search_result, page_start.saturating_sub(length - 1), limit) {
+                mark(result, result + length);
+            }
+        }
+        if let Some(state) = &self.replace_state {
+            mark(self.cursor_index + 1, self.cursor_index + state.find_length());
+        }
+        highlights
+    }
+
+    fn reload(&mut self) {
+        match Buffer::open(&self.file_name) {
+            Ok(buffer) => {
+                self.buffer = buffer;
+                self.history.clear();
+                // The marks moved by the edits are back where they were saved
+                self.load_marks();
             }
+            Err(e) => self.show_error(EditorError::Open(self.file_name.clone(), e).to_string()),
+        }
+    }
 
+    fn write(&mut self) {
+        if let Err(e) = save(&self.buffer, &self.file_name, self.backup) {
+            self.show_error(EditorError::Write(self.file_name.clone(), e).to_string());
+            return;
         }
+        // The buffer is kept as it is, since the history refers to its pieces:
+        // the replaced file stays readable through the handle kept open on it
+        self.history.mark_saved();
+        self.save_marks();
+        self.refresh = true;
+    }
 
-        stdout.queue(SetColors(Colors::new(Reset, Reset)))?;
-        stdout.flush()?;
-        Ok(())
+    // Report the last failed read of the file, if any
+    fn check_read_error(&mut self) {
+        if let Some(e) = self.buffer.take_error() {
+            self.show_error(EditorError::Read(self.file_name.clone(), e).to_string());
+        }
     }
 
-    fn reload(&mut self) {
-        let mut f = File::open(&self.file_name).unwrap();
-        self.buffer.clear();
-        f.read_to_end(&mut self.buffer).unwrap();
+    // The last position of the cursor: after the last byte when inserting
+    fn last_position(&self) -> usize {
+        if self.insert && matches!(self.mode, Mode::Edit | Mode::AsciiEdit) {
+            self.buffer.len()
+        } else {
+            self.buffer.len().saturating_sub(1)
+        }
+    }
+
+    fn has_error(&self) -> bool {
+        matches!(self.message, Some(Message::Error(_)))
+    }
+
+    /// Whether the keys go to the normal mode, and not to a text being typed
+    /// or to a command waiting for the rest of it, like the register of '"a'.
+    pub fn is_normal_mode(&self) -> bool {
+        self.mode == Mode::Normal
+            && !self.is_searching()
+            && self.count.is_none()
+            && self.pending.is_none()
+            && self.register.is_none()
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.history.is_modified()
+    }
+
+    /// Ask for a confirmation before quitting, when some files are not saved.
+    pub fn confirm_exit(&mut self, unsaved_files: usize) {
+        self.exit = false;
+        self.unsaved_files = unsaved_files;
+        self.mode = Mode::Quit;
+        self.refresh = true;
+    }
+
+    // Replace `length` bytes at `offset` by `bytes`, and record it in the history.
+    // A plain overwrite uses `length == bytes.len()`, an insertion `length == 0`
+    // and a deletion an empty `bytes`.
+    fn replace_bytes(&mut self, offset: usize, length: usize, bytes: &[u8], group: bool) {
+        let old = self.buffer.replace(offset, length, bytes);
+        let new = self.buffer.span(offset, bytes.len());
+        self.marks.shift(offset, length, bytes.len());
+        self.jumps.shift(offset, length, bytes.len());
+        self.history.record(Change::new(offset, old, new, self.cursor_index), group);
     }
 
 }
 
-fn write_nibble(buffer: &mut Vec<u8>, position: usize, value: u8, nibble_hl: u8) {
-    let nibble_bits: u8 = value << 4 * (1 - nibble_hl);
-    let mask: u8 = 0x0F << 4 * nibble_hl;
+// Search results starting between `start` and `end`: the results are
+// sorted, but rotated by the moves to the next and previous ones
+fn results_between(results: &[usize], start: usize, end: usize) -> Vec<usize> {
+    let Some(first) = results.first() else {
+        return Vec::new();
+    };
+    let (rotated, wrapped) = results.split_at(results.partition_point(|r| r >= first));
+    [rotated, wrapped]
+        .iter()
+        .flat_map(|part| {
+            let from = part.partition_point(|r| *r < start);
+            let to = part.partition_point(|r| *r < end);
+            part[from..to].iter().copied()
+        })
+        .collect()
+}
+
+fn write_nibble(buffer: &mut [u8], position: usize, value: u8, nibble_hl: u8) {
+    let nibble_bits: u8 = value << (4 * (1 - nibble_hl));
+    let mask: u8 = 0x0F << (4 * nibble_hl);
     buffer[position] &= mask;
     buffer[position] |= nibble_bits;
 }
 
-fn is_printable_code(c: u8) -> bool {
-    (c >= 32 && c <= 126) || (c > 127 && c < 255)
-}
@@ -1,12 +1,241 @@
-use crossterm::event::KeyCode;
-use std::io::Write;
-use std::fs::File;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::cmp;
 use crate::editor::{Editor, Mode};
+use crate::editor::{macros, marks};
+use crate::editor::pattern::Pattern;
+
+// Larger counts are taken as this one, so that a command can't run for ever
+const MAX_COUNT: usize = 100_000;
 
 impl Editor {
-    pub fn normal_inputs(&mut self, key_code: KeyCode) {
+    pub fn normal_inputs(&mut self, key_event: KeyEvent) {
+        // The keys with CONTROL are commands of their own, which also
+        // cancel a register or a prefix being typed
+        if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+            self.pending = None;
+            self.register = None;
+            let count = self.count.take().unwrap_or(1);
+            for _ in 0..count {
+                if !self.control_command(key_event.code) {
+                    break;
+                }
+            }
+            self.refresh = true;
+            return;
+        }
+        if let Some(prefix) = self.pending.take() {
+            // The count typed before '"' is kept for the command after it
+            if prefix == '"' {
+                self.select_register(key_event.code);
+                return;
+            }
+            let count = self.count.take().unwrap_or(1);
+            self.register = None;
+            self.register_command(prefix, key_event.code, count);
+            self.refresh = true;
+            return;
+        }
+        // A count typed before a command
+        if let KeyCode::Char(c @ '0'..='9') = key_event.code {
+            if c != '0' || self.count.is_some() {
+                let digit = c as usize - '0' as usize;
+                self.count = Some(cmp::min(self.count.unwrap_or(0) * 10 + digit, MAX_COUNT));
+                self.refresh = true;
+                return;
+            }
+        }
+        match key_event.code {
+            KeyCode::Char('q') if self.recording.is_some() => {
+                self.count = None;
+                self.stop_recording();
+                return;
+            }
+            // Commands followed by a register, like '@a'
+            KeyCode::Char(c @ ('q' | '@' | 'm' | '\'' | '"')) => {
+                self.pending = Some(c);
+                self.refresh = true;
+                return;
+            }
+            _ => {}
+        }
+        let count = self.count.take().unwrap_or(1);
+        match key_event.code {
+            KeyCode::Char('x') => self.cut(count),
+            KeyCode::Char('y') => self.yank(count),
+            KeyCode::Char('p') => self.paste(count, false),
+            KeyCode::Char('P') => self.paste(count, true),
+            KeyCode::Char('.') => self.repeat_change(count),
+            // Until there is nothing left to undo
+            KeyCode::Char('u') => {
+                for _ in 0..count {
+                    if !self.undo() {
+                        break;
+                    }
+                }
+            }
+            _ if is_repeatable(&key_event) => {
+                // Until the command has no effect, like a motion at the end of the file
+                for _ in 0..count {
+                    let state = (self.cursor_index, self.page, self.history.recorded());
+                    self.normal_command(key_event);
+                    if state == (self.cursor_index, self.page, self.history.recorded()) {
+                        break;
+                    }
+                }
+            }
+            _ => self.normal_command(key_event),
+        }
+        // The register is only for the command typed right after it, or
+        // for the selection it starts
+        if self.mode != Mode::Selection {
+            self.register = None;
+        }
+    }
+
+    // A CONTROL key, returning whether it had an effect
+    fn control_command(&mut self, key_code: KeyCode) -> bool {
+        let position = self.cursor_index;
+        match key_code {
+            KeyCode::Char('r') => return self.redo(),
+            KeyCode::Char('o') => self.jump_back(),
+            KeyCode::Char('i') => self.jump_forward(),
+            KeyCode::Char('t') => self.return_from_pointer(),
+            _ => {}
+        }
+        self.cursor_index != position
+    }
+
+    fn normal_command(&mut self, key_event: KeyEvent) {
+        if self.motion_inputs(key_event.code) {
+            return;
+        }
+        match key_event.code {
+            // CTRL-i, for the terminals which can't tell it from <TAB>
+            KeyCode::Tab => self.jump_forward(),
+            KeyCode::Char('Q') => self.run_command("quit"),
+            KeyCode::Char('i') => {
+                self.insert = true;
+                self.mode = Mode::Edit;
+                self.refresh = true;
+            }
+            KeyCode::Char('I') => {
+                self.insert = true;
+                self.mode = Mode::AsciiEdit;
+                self.refresh = true;
+            }
+            KeyCode::Char('R') => {
+                self.insert = false;
+                self.mode = Mode::Edit;
+                self.refresh = true;
+            }
+            KeyCode::Char('a') => {
+                self.replace_bytes(self.cursor_index, 0, &[0], false);
+                self.refresh = true;
+            }
+            KeyCode::Char('v') => {
+                self.cursor_start = self.cursor_index;
+                self.mode = Mode::Selection;
+                self.refresh = true;
+            }
+            KeyCode::Char('w') => {
+                self.run_command("write");
+            }
+            KeyCode::Char(':') => {
+                self.input.clear();
+                self.command_history_index = self.command_history.len();
+                self.mode = Mode::Command;
+                self.refresh = true;
+            }
+            KeyCode::Char('f') => self.follow_pointer(),
+            KeyCode::Char('J') => {
+                self.input.clear();
+                self.jump_return = Mode::Normal;
+                self.mode = Mode::Jump;
+                self.refresh = true;
+            }
+            KeyCode::Char('s') => {
+                self.search_pattern = Pattern::default();
+                self.search_result = Vec::new();
+                self.mode = Mode::Search;
+                self.refresh = true;
+            }
+            KeyCode::Char('/') => {
+                self.search_text.clear();
+                self.search_pattern = Pattern::default();
+                self.search_result = Vec::new();
+                self.mode = Mode::TextSearch;
+                self.refresh = true;
+            }
+            KeyCode::Char('#') => {
+                self.input.clear();
+                self.search_pattern = Pattern::default();
+                self.search_result = Vec::new();
+                self.mode = Mode::NumberSearch;
+                self.refresh = true;
+            }
+            KeyCode::Char('D') => {
+                self.show_inspector = !self.show_inspector;
+                self.refresh = true;
+            }
+            KeyCode::Char('=') => {
+                self.show_inspector = true;
+                self.mode = Mode::Inspector;
+                self.refresh = true;
+            }
+            KeyCode::Char('W') => {
+                self.layout.next_width();
+                self.show_info(self.layout.name());
+            }
+            KeyCode::Char('C') => {
+                self.layout.next_group();
+                self.show_info(self.layout.name());
+            }
+            KeyCode::Char('|') => {
+                self.layout.gap = !self.layout.gap;
+                self.show_info(self.layout.name());
+            }
+            KeyCode::Char('F') => {
+                self.layout.radix = self.layout.radix.next();
+                self.nibble_index = 0;
+                self.show_info(format!("{} display", self.layout.radix.name()));
+            }
+            KeyCode::Char('E') => {
+                self.charset = self.charset.next();
+                self.show_info(format!("{} text", self.charset.name()));
+            }
+            KeyCode::Char('S') => {
+                self.input.clear();
+                self.replace_range = None;
+                self.mode = Mode::Replace;
+                self.refresh = true;
+            }
+            KeyCode::Char('r') => {
+                self.run_command("reload");
+            }
+            KeyCode::Char('M') => {
+                self.marks_row = 0;
+                self.mode = Mode::Marks;
+                self.refresh = true;
+            }
+            KeyCode::Char('?') => {
+                self.mode = Mode::Help;
+                self.refresh = true;
+            }
+            KeyCode::Esc => {
+                self.count = None;
+                self.nibble_index = 0;
+                self.mode = Mode::Normal;
+                self.refresh = true;
+            }
+            _ => {}
+        }
+
+    }
+
+    /// Move the cursor, in normal and selection modes. Returns whether the
+    /// key was a motion.
+    pub fn motion_inputs(&mut self, key_code: KeyCode) -> bool {
         match key_code {
-            KeyCode::Char('q') => self.exit = true,
             KeyCode::Char('h') | KeyCode::Left => {
                 if self.cursor_index > 0 {
                     self.nibble_index = 0;
@@ -15,33 +244,33 @@ impl Editor {
                 }
             }
             KeyCode::Char('j') | KeyCode::Down => {
-                if self.buffer.len() > 16 && self.cursor_index < self.buffer.len() - 16 {
-                    self.cursor_index += 16;
+                if self.cursor_index < self.buffer.len().saturating_sub(self.bytes_per_line) {
+                    self.cursor_index += self.bytes_per_line;
                     self.nibble_index = 0;
                     self.refresh = true;
                 }
             }
             KeyCode::Char('k') | KeyCode::Up => {
-                if self.cursor_index >= 16 {
+                if self.cursor_index >= self.bytes_per_line {
                     self.nibble_index = 0;
-                    self.cursor_index -= 16;
+                    self.cursor_index -= self.bytes_per_line;
                     self.refresh = true;
                 }
             }
             KeyCode::Char('l') | KeyCode::Right => {
-                if self.cursor_index < self.buffer.len() - 1 {
+                if self.cursor_index < self.buffer.len().saturating_sub(1) {
                     self.nibble_index = 0;
                     self.cursor_index += 1;
                     self.refresh = true;
                 }
             }
             KeyCode::Char('(') => {
-                self.cursor_index = self.cursor_index / 16 * 16;
+                self.cursor_index = self.cursor_index / self.bytes_per_line * self.bytes_per_line;
                 self.nibble_index = 0;
                 self.refresh = true;
             }
             KeyCode::Char(')') => {
-                self.cursor_index = self.cursor_index / 16 * 16 + 15;
+                self.cursor_index = self.cursor_index / self.bytes_per_line * self.bytes_per_line + self.bytes_per_line - 1;
                 self.nibble_index = 0;
                 self.refresh = true;
             }
@@ -57,7 +286,6 @@ impl Editor {
             }
             KeyCode::Char('b') => {
                 if self.page > 0 {
-
                     self.page -= 1;
                     self.cursor_index -= self.page_size;
                     self.refresh = true;
@@ -70,106 +298,105 @@ impl Editor {
                     self.refresh = true;
                 }
             }
-            KeyCode::Char('g') => {
-                self.cursor_index = 0;
-                self.refresh = true;
-                self.nibble_index = 0;
-            }
-            KeyCode::Char('G') => {
-                self.cursor_index = self.buffer.len() - 1;
-                self.nibble_index = 0;
-                self.refresh = true;
-            }
-            KeyCode::Char('i') => {
-                self.mode = Mode::Edit;
-                self.refresh = true;
-            }
-            KeyCode::Char('I') => {
-                self.mode = Mode::AsciiEdit;
-                self.refresh = true;
-            }
-            KeyCode::Char('a') => {
-                self.buffer.insert(self.cursor_index, 0);
-                self.refresh = true;
-            }
-            KeyCode::Char('x') => {
-                if self.buffer.len() > 0 {
-                    self.clipboard.clear();
-                    self.clipboard.push(self.buffer[self.cursor_index]);
-                    self.buffer.remove(self.cursor_index);
-                    self.refresh = true;
-                }
-            }
-            KeyCode::Char('y') => {
-                self.clipboard.clear();
-                if self.mode == Mode::Selection {
-                    let clipboard_range = self.cursor_index - self.cursor_start + 1;
-                    for n in 0..clipboard_range {
-                        self.clipboard.push(self.buffer[self.cursor_index - (clipboard_range - 1 - n)]);
-                    }
-                    self.mode = Mode::Normal;
-                    self.refresh = true;
-                } else {
-                    self.clipboard.push(self.buffer[self.cursor_index]);
-                }
-            }
-            KeyCode::Char('p') => {
-                for n in 0..self.clipboard.len() {
-                    if self.cursor_index + n < self.buffer.len() {
-
-                        self.buffer[self.cursor_index + n] = self.clipboard[n];
-                    }
-                }
-                self.refresh = true;
-            }
-            KeyCode::Char('v') => {
-                self.cursor_start = self.cursor_index;
-                self.mode = Mode::Selection;
-                self.refresh = true;
-            }
-            KeyCode::Char('w') => {
-                let mut f = File::create(&self.file_name).unwrap();
-                f.write(&self.buffer).expect("impossible to write file");
-            }
-            KeyCode::Char('J') => {
-                self.mode = Mode::Jump;
-                self.refresh = true;
-            }
-            KeyCode::Char('s') => {
-                self.search_result = Vec::new();
-                self.mode = Mode::Search;
-                self.refresh = true;
-            }
+            KeyCode::Char('g') => self.jump_to(0),
+            KeyCode::Char('G') => self.jump_to(self.buffer.len().saturating_sub(1)),
             KeyCode::Char('>') => {
                 if !self.search_result.is_empty() {
                     self.search_result.rotate_left(1);
-                    self.cursor_index = self.search_result[0] as usize;
-                    self.refresh = true;
+                    self.jump_to(self.search_result[0]);
                 }
             }
             KeyCode::Char('<') => {
                 if !self.search_result.is_empty() {
                     self.search_result.rotate_right(1);
-                    self.cursor_index = self.search_result[0] as usize;
-                    self.refresh = true;
+                    self.jump_to(self.search_result[0]);
                 }
             }
-            KeyCode::Char('r') => {
-                self.reload();
-                self.refresh = true;
-            }
-            KeyCode::Char('?') => {
-                self.mode = Mode::Help;
-                self.refresh = true;
-            }
-            KeyCode::Esc => {
-                self.nibble_index = 0;
-                self.mode = Mode::Normal;
-                self.refresh = true;
+            _ => return false,
+        }
+        true
+    }
+
+    // The key after a command waiting for a register
+    fn register_command(&mut self, prefix: char, key_code: KeyCode, count: usize) {
+        let KeyCode::Char(register) = key_code else {
+            // <ESC> cancels the command
+            return;
+        };
+        match prefix {
+            'q' if macros::is_register(register) => self.start_recording(register),
+            'm' if marks::is_mark_name(register) => self.set_mark(register),
+            '\'' if marks::is_mark_name(register) => self.jump_to_mark(register),
+            '@' if macros::is_register(register) || register == '@' => self.play_macro(register, count),
+            'm' | '\'' => self.show_error(format!("Invalid mark '{}'", register)),
+            _ => self.show_error(format!("Invalid register '{}'", register)),
+        }
+    }
+
+    // Type again the keys of the last change, `count` times
+    fn repeat_change(&mut self, count: usize) {
+        let keys = self.last_change.clone();
+        if keys.is_empty() {
+            return;
+        }
+        self.replaying = true;
+        for _ in 0..count {
+            for key in &keys {
+                self.replay_key(*key);
             }
-            _ => {}
+            self.history.seal();
+        }
+        self.replaying = false;
+        // The '.' is not a change to repeat
+        self.change_keys.clear();
+        self.change_start = self.history.recorded();
+        self.refresh = true;
+    }
+
+    // Cut `count` bytes
+    fn cut(&mut self, count: usize) {
+        let end = cmp::min(self.cursor_index.saturating_add(count), self.buffer.len());
+        if end > self.cursor_index {
+            self.store_register(self.buffer.slice(self.cursor_index, end));
+            self.replace_bytes(self.cursor_index, end - self.cursor_index, &[], false);
+            self.refresh = true;
         }
+    }
 
+    // Copy `count` bytes
+    fn yank(&mut self, count: usize) {
+        let end = cmp::min(self.cursor_index.saturating_add(count), self.buffer.len());
+        if end > self.cursor_index {
+            self.store_register(self.buffer.slice(self.cursor_index, end));
+        }
     }
 
+    // Paste a register `count` times, inserted at the cursor or over the
+    // bytes there, the file growing when they go past its end
+    fn paste(&mut self, count: usize, insert: bool) {
+        let Some(register) = self.take_register() else {
+            return;
+        };
+        let Some(size) = register.len().checked_mul(count) else {
+            self.show_error(format!("Too many bytes to paste ({} x {})", register.len(), count));
+            return;
+        };
+        let bytes: Vec<u8> = register.iter().copied().cycle().take(size).collect();
+        let length = if insert { 0 } else { cmp::min(bytes.len(), self.buffer.len() - self.cursor_index) };
+        self.replace_bytes(self.cursor_index, length, &bytes, false);
+        self.refresh = true;
+    }
+}
+
+// Commands done again for each unit of their count
+fn is_repeatable(key_event: &KeyEvent) -> bool {
+    matches!(
+        key_event.code,
+        KeyCode::Char('h' | 'j' | 'k' | 'l' | '(' | ')' | '[' | ']' | 'n' | 'b' | '>' | '<' | 'a')
+            | KeyCode::Left
+            | KeyCode::Down
+            | KeyCode::Up
+            | KeyCode::Right
+            | KeyCode::Tab
+    )
 }
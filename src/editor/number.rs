@@ -0,0 +1,280 @@
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    pub fn from_name(name: &str) -> Option<Endianness> {
+        match name {
+            "le" => Some(Endianness::Little),
+            "be" => Some(Endianness::Big),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Endianness::Little => "le",
+            Endianness::Big => "be",
+        }
+    }
+}
+
+/// The types a value can be read as, or written with.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NumberType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+pub const NUMBER_TYPES: [NumberType; 10] = [
+    NumberType::U8,
+    NumberType::I8,
+    NumberType::U16,
+    NumberType::I16,
+    NumberType::U32,
+    NumberType::I32,
+    NumberType::U64,
+    NumberType::I64,
+    NumberType::F32,
+    NumberType::F64,
+];
+
+impl NumberType {
+    pub fn from_name(name: &str) -> Option<NumberType> {
+        NUMBER_TYPES.iter().copied().find(|t| t.name() == name)
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            NumberType::U8 => "u8",
+            NumberType::I8 => "i8",
+            NumberType::U16 => "u16",
+            NumberType::I16 => "i16",
+            NumberType::U32 => "u32",
+            NumberType::I32 => "i32",
+            NumberType::U64 => "u64",
+            NumberType::I64 => "i64",
+            NumberType::F32 => "f32",
+            NumberType::F64 => "f64",
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            NumberType::U8 | NumberType::I8 => 1,
+            NumberType::U16 | NumberType::I16 => 2,
+            NumberType::U32 | NumberType::I32 | NumberType::F32 => 4,
+            NumberType::U64 | NumberType::I64 | NumberType::F64 => 8,
+        }
+    }
+
+    fn is_signed(&self) -> bool {
+        matches!(self, NumberType::I8 | NumberType::I16 | NumberType::I32 | NumberType::I64)
+    }
+
+    /// Bytes of a value written as text: a decimal, `0x` hex, `0o` octal or
+    /// `0b` binary integer, or a decimal number for the float types.
+    pub fn encode(&self, text: &str, endianness: Endianness) -> Result<Vec<u8>, String> {
+        let mut bytes = match self {
+            NumberType::F32 => (parse_float(text)? as f32).to_le_bytes().to_vec(),
+            NumberType::F64 => parse_float(text)?.to_le_bytes().to_vec(),
+            _ => {
+                let value = parse_integer(text).ok_or(format!("Invalid number '{}'", text))?;
+                let bits = self.size() as u32 * 8;
+                // Hex, octal and binary values can also give the raw bits of a negative number
+                let max = if self.is_signed() && !has_radix_prefix(text) {
+                    (1i128 << (bits - 1)) - 1
+                } else {
+                    (1i128 << bits) - 1
+                };
+                let min = if self.is_signed() { -(1i128 << (bits - 1)) } else { 0 };
+                if value < min || value > max {
+                    return Err(format!("{} is out of the {} range", text, self.name()));
+                }
+                value.to_le_bytes()[..self.size()].to_vec()
+            }
+        };
+        if endianness == Endianness::Big {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+}
+
+impl NumberType {
+    /// Raw bits of the first `size` bytes, for the integer types.
+    pub fn read_bits(&self, bytes: &[u8], endianness: Endianness) -> Option<u64> {
+        let bytes = bytes.get(..self.size())?;
+        let fold = |value: u64, byte: &u8| value << 8 | *byte as u64;
+        Some(match endianness {
+            Endianness::Little => bytes.iter().rev().fold(0, fold),
+            Endianness::Big => bytes.iter().fold(0, fold),
+        })
+    }
+
+    /// Text of the value read from the first `size` bytes, if there are enough.
+    pub fn decode(&self, bytes: &[u8], endianness: Endianness) -> Option<String> {
+        let mut raw = [0u8; 8];
+        raw[..self.size()].copy_from_slice(bytes.get(..self.size())?);
+        if endianness == Endianness::Big {
+            raw[..self.size()].reverse();
+        }
+        let text = match self {
+            NumberType::U8 => raw[0].to_string(),
+            NumberType::I8 => (raw[0] as i8).to_string(),
+            NumberType::U16 => u16::from_le_bytes([raw[0], raw[1]]).to_string(),
+            NumberType::I16 => i16::from_le_bytes([raw[0], raw[1]]).to_string(),
+            NumberType::U32 => u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]).to_string(),
+            NumberType::I32 => i32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]).to_string(),
+            NumberType::U64 => u64::from_le_bytes(raw).to_string(),
+            NumberType::I64 => i64::from_le_bytes(raw).to_string(),
+            NumberType::F32 => format_float(f32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]])),
+            NumberType::F64 => format_float(f64::from_le_bytes(raw)),
+        };
+        Some(text)
+    }
+}
+
+// Very large or small floats are written with an exponent
+fn format_float<T: Into<f64> + fmt::Display + fmt::LowerExp + Copy>(value: T) -> String {
+    let magnitude = value.into().abs();
+    if magnitude != 0.0 && magnitude.is_finite() && !(1e-4..1e9).contains(&magnitude) {
+        format!("{:e}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+/// Parse an integer, with an optional sign and `0x`, `0o` or `0b` prefix.
+pub fn parse_integer(text: &str) -> Option<i128> {
+    let text = text.replace('_', "");
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(&text)),
+    };
+    let lower = digits.to_ascii_lowercase();
+    let (radix, digits) = if let Some(d) = lower.strip_prefix("0x") {
+        (16, d)
+    } else if let Some(d) = lower.strip_prefix("0o") {
+        (8, d)
+    } else if let Some(d) = lower.strip_prefix("0b") {
+        (2, d)
+    } else {
+        (10, lower.as_str())
+    };
+    // from_str_radix would take a second sign
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let value = i128::from_str_radix(digits, radix).ok()?;
+    if negative { value.checked_neg() } else { Some(value) }
+}
+
+fn has_radix_prefix(text: &str) -> bool {
+    let lower = text.trim_start_matches(['-', '+']).to_ascii_lowercase();
+    lower.starts_with("0x") || lower.starts_with("0o") || lower.starts_with("0b")
+}
+
+fn parse_float(text: &str) -> Result<f64, String> {
+    text.parse::<f64>().map_err(|_| format!("Invalid number '{}'", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_integer_radixes() {
+        assert_eq!(parse_integer("1234"), Some(1234));
+        assert_eq!(parse_integer("+12"), Some(12));
+        assert_eq!(parse_integer("-12"), Some(-12));
+        assert_eq!(parse_integer("0x1F"), Some(31));
+        assert_eq!(parse_integer("-0x10"), Some(-16));
+        assert_eq!(parse_integer("0o17"), Some(15));
+        assert_eq!(parse_integer("0b101"), Some(5));
+        assert_eq!(parse_integer("1_000"), Some(1000));
+    }
+
+    #[test]
+    fn parse_integer_rejects_invalid_text() {
+        assert_eq!(parse_integer(""), None);
+        assert_eq!(parse_integer("0x"), None);
+        assert_eq!(parse_integer("12a"), None);
+        assert_eq!(parse_integer("0b102"), None);
+        assert_eq!(parse_integer("--5"), None);
+        assert_eq!(parse_integer("-+5"), None);
+        assert_eq!(parse_integer("+-5"), None);
+        assert_eq!(parse_integer("0x-5"), None);
+        assert_eq!(parse_integer("0x+5"), None);
+    }
+
+    #[test]
+    fn parse_integer_limits() {
+        assert_eq!(parse_integer(&i128::MAX.to_string()), Some(i128::MAX));
+        assert_eq!(parse_integer(&format!("-{}", i128::MAX)), Some(-i128::MAX));
+        assert_eq!(parse_integer("170141183460469231731687303715884105728"), None);
+        assert_eq!(parse_integer("--170141183460469231731687303715884105728"), None);
+    }
+
+    fn encode(number_type: NumberType, text: &str) -> Result<Vec<u8>, String> {
+        number_type.encode(text, Endianness::Little)
+    }
+
+    #[test]
+    fn encode_unsigned_limits() {
+        for (number_type, max) in [
+            (NumberType::U8, u8::MAX as u64),
+            (NumberType::U16, u16::MAX as u64),
+            (NumberType::U32, u32::MAX as u64),
+            (NumberType::U64, u64::MAX),
+        ] {
+            let size = number_type.size();
+            assert_eq!(encode(number_type, "0"), Ok(vec![0; size]));
+            assert_eq!(encode(number_type, &max.to_string()), Ok(vec![0xff; size]));
+            assert!(encode(number_type, &(max as u128 + 1).to_string()).is_err());
+            assert!(encode(number_type, "-1").is_err());
+        }
+    }
+
+    #[test]
+    fn encode_signed_limits() {
+        for (number_type, min, max) in [
+            (NumberType::I8, i8::MIN as i64, i8::MAX as i64),
+            (NumberType::I16, i16::MIN as i64, i16::MAX as i64),
+            (NumberType::I32, i32::MIN as i64, i32::MAX as i64),
+            (NumberType::I64, i64::MIN, i64::MAX),
+        ] {
+            let size = number_type.size();
+            assert_eq!(encode(number_type, &min.to_string()), Ok(min.to_le_bytes()[..size].to_vec()));
+            assert_eq!(encode(number_type, &max.to_string()), Ok(max.to_le_bytes()[..size].to_vec()));
+            assert_eq!(encode(number_type, "-1"), Ok(vec![0xff; size]));
+            assert!(encode(number_type, &(min as i128 - 1).to_string()).is_err());
+            assert!(encode(number_type, &(max as i128 + 1).to_string()).is_err());
+            // The raw bits of a negative number
+            let all_bits = format!("0x{}", "ff".repeat(size));
+            assert_eq!(encode(number_type, &all_bits), Ok(vec![0xff; size]));
+        }
+    }
+
+    #[test]
+    fn encode_endianness_and_floats() {
+        assert_eq!(NumberType::U32.encode("0x01020304", Endianness::Big), Ok(vec![1, 2, 3, 4]));
+        assert_eq!(NumberType::U32.encode("0x01020304", Endianness::Little), Ok(vec![4, 3, 2, 1]));
+        assert_eq!(encode(NumberType::F32, "1.5"), Ok(1.5f32.to_le_bytes().to_vec()));
+        assert_eq!(encode(NumberType::F64, "-0.25"), Ok((-0.25f64).to_le_bytes().to_vec()));
+        assert!(encode(NumberType::F32, "one").is_err());
+        assert!(encode(NumberType::U8, "0x-1").is_err());
+    }
+}
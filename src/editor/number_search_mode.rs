@@ -0,0 +1,61 @@
+use crossterm::event::KeyCode;
+use crate::editor::{Editor, Mode};
+use crate::editor::number::{Endianness, NumberType};
+use crate::editor::pattern::Pattern;
+
+impl Editor {
+    pub fn number_search_inputs(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                self.refresh = true;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.refresh = true;
+            }
+            KeyCode::Enter => {
+                self.mode = Mode::Normal;
+                match parse_number_search(&self.input) {
+                    Ok(patterns) => {
+                        self.search_pattern = patterns[0].clone();
+                        self.search_any(patterns);
+                    }
+                    Err(e) => self.show_error(e),
+                }
+                self.refresh = true;
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.refresh = true;
+            }
+            _ => {}
+        }
+    }
+}
+
+// Read `VALUE TYPE [le|be]` and give the patterns to search.
+// Without endianness, both are searched.
+fn parse_number_search(input: &str) -> Result<Vec<Pattern>, String> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let (value, type_name, endianness) = match words.as_slice() {
+        [value, type_name] => (*value, *type_name, None),
+        [value, type_name, endianness] => (*value, *type_name, Some(*endianness)),
+        _ => return Err("Expected: value type [le|be]".to_string()),
+    };
+    let number_type = NumberType::from_name(type_name)
+        .ok_or(format!("Unknown type '{}' (u8..u64, i8..i64, f32, f64)", type_name))?;
+    let endiannesses = match endianness {
+        Some(name) => vec![Endianness::from_name(name).ok_or(format!("Unknown endianness '{}'", name))?],
+        None => vec![Endianness::Little, Endianness::Big],
+    };
+
+    let mut patterns: Vec<Pattern> = Vec::new();
+    for endianness in endiannesses {
+        let bytes = number_type.encode(value, endianness)?;
+        if !patterns.iter().any(|p| p.bytes() == bytes.as_slice()) {
+            patterns.push(Pattern::from_bytes(bytes));
+        }
+    }
+    Ok(patterns)
+}
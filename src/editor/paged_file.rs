@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::rc::Rc;
+
+const PAGE_SIZE: usize = 64 * 1024;
+const CACHED_PAGES: usize = 64;
+
+/// Read-only access to a file, page by page.
+///
+/// Only the pages that are actually read are loaded, and at most
+/// `CACHED_PAGES` of them are kept in memory, so files larger than the
+/// available memory can be opened.
+pub struct PagedFile {
+    file: RefCell<File>,
+    length: usize,
+    // Most recently used pages are at the end
+    cache: RefCell<Vec<(usize, Rc<Vec<u8>>)>>,
+}
+
+impl PagedFile {
+    pub fn open(file_name: &str) -> io::Result<PagedFile> {
+        let file = File::open(file_name)?;
+        let length = file.metadata()?.len() as usize;
+        Ok(PagedFile {
+            file: RefCell::new(file),
+            length,
+            cache: RefCell::new(Vec::new()),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    /// Append the bytes in `start..end` to `bytes`.
+    pub fn read(&self, start: usize, end: usize, bytes: &mut Vec<u8>) -> io::Result<()> {
+        let mut position = start;
+        while position < end {
+            let page = self.page(position / PAGE_SIZE)?;
+            let from = position % PAGE_SIZE;
+            let to = cmp::min(page.len(), from + end - position);
+            bytes.extend_from_slice(&page[from..to]);
+            position += to - from;
+        }
+        Ok(())
+    }
+
+    /// Copy the bytes in `start..end` to `writer`, without going through the cache.
+    pub fn copy_to(&self, start: usize, end: usize, writer: &mut impl Write) -> io::Result<()> {
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(start as u64))?;
+        let mut chunk = vec![0; PAGE_SIZE];
+        let mut position = start;
+        while position < end {
+            let length = cmp::min(PAGE_SIZE, end - position);
+            file.read_exact(&mut chunk[..length])?;
+            writer.write_all(&chunk[..length])?;
+            position += length;
+        }
+        Ok(())
+    }
+
+    fn page(&self, number: usize) -> io::Result<Rc<Vec<u8>>> {
+        let mut cache = self.cache.borrow_mut();
+        if let Some(index) = cache.iter().position(|(n, _)| *n == number) {
+            let entry = cache.remove(index);
+            let page = entry.1.clone();
+            cache.push(entry);
+            return Ok(page);
+        }
+
+        let start = number * PAGE_SIZE;
+        let mut page = vec![0; cmp::min(PAGE_SIZE, self.length - start)];
+        let mut file = self.file.borrow_mut();
+        file.seek(SeekFrom::Start(start as u64))?;
+        file.read_exact(&mut page)?;
+
+        if cache.len() >= CACHED_PAGES {
+            cache.remove(0);
+        }
+        let page = Rc::new(page);
+        cache.push((number, page.clone()));
+        Ok(page)
+    }
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+
+use crate::editor::write_nibble;
+
+/// A byte pattern where each nibble can be a wildcard.
+///
+/// A byte of the file matches the pattern byte when the bits set in the mask
+/// are equal, so a `0x00` mask matches any byte, `0xf0` only checks the high nibble.
+#[derive(Clone, Default)]
+pub struct Pattern {
+    bytes: Vec<u8>,
+    masks: Vec<u8>,
+}
+
+impl Pattern {
+    pub fn from_bytes(bytes: Vec<u8>) -> Pattern {
+        let masks = vec![0xff; bytes.len()];
+        Pattern { bytes, masks }
+    }
+
+    /// Let the ASCII letter at `index` match both cases,
+    /// as they only differ by the `0x20` bit.
+    pub fn ignore_case_at(&mut self, index: usize) {
+        self.masks[index] &= !0x20;
+    }
+
+    pub fn push_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+        self.masks.extend(bytes.iter().map(|_| 0xff));
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn has_wildcards(&self) -> bool {
+        self.masks.iter().any(|mask| *mask != 0xff)
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Set a nibble of the last byte (a new byte is started on the high nibble).
+    /// A `None` value is a wildcard.
+    pub fn push_nibble(&mut self, value: Option<u8>, nibble_index: u8) {
+        if nibble_index == 0 {
+            self.bytes.push(0);
+            self.masks.push(0);
+        }
+        let position = self.bytes.len() - 1;
+        write_nibble(&mut self.bytes, position, value.unwrap_or(0), nibble_index);
+        write_nibble(&mut self.masks, position, if value.is_some() { 0x0f } else { 0 }, nibble_index);
+    }
+
+    pub fn pop(&mut self) {
+        self.bytes.pop();
+        self.masks.pop();
+    }
+
+    /// Whether `byte` matches the byte at `position` in the pattern.
+    pub fn matches_at(&self, position: usize, byte: u8) -> bool {
+        byte & self.masks[position] == self.bytes[position] & self.masks[position]
+    }
+
+    pub fn matches(&self, window: &[u8]) -> bool {
+        window.len() == self.bytes.len()
+            && window
+                .iter()
+                .zip(self.bytes.iter().zip(self.masks.iter()))
+                .all(|(byte, (pattern, mask))| byte & mask == pattern & mask)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (byte, mask) in self.bytes.iter().zip(self.masks.iter()) {
+            let high = if mask & 0xf0 == 0 { '?' } else { hex_digit(byte >> 4) };
+            let low = if mask & 0x0f == 0 { '?' } else { hex_digit(byte & 0x0f) };
+            write!(f, " {}{}", high, low)?;
+        }
+        Ok(())
+    }
+}
+
+fn hex_digit(value: u8) -> char {
+    char::from_digit(value as u32, 16).unwrap_or('?')
+}
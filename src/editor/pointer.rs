@@ -0,0 +1,51 @@
+use crate::editor::Editor;
+use crate::editor::number::{Endianness, NumberType};
+
+/// Type and endianness of a pointer, written like `u32le` or `u64be`,
+/// the endianness being optional.
+pub fn parse_pointer_format(name: &str) -> Option<(NumberType, Option<Endianness>)> {
+    let (type_name, endianness) = match name.strip_suffix("le").or_else(|| name.strip_suffix("be")) {
+        Some(type_name) => (type_name, Some(Endianness::from_name(&name[type_name.len()..])?)),
+        None => (name, None),
+    };
+    let number_type = NumberType::from_name(type_name)?;
+    matches!(number_type, NumberType::U16 | NumberType::U32 | NumberType::U64).then_some((number_type, endianness))
+}
+
+impl Editor {
+    /// Name of the pointers followed, with their base.
+    pub fn pointer_name(&self) -> String {
+        let mut name = format!("{}{} pointers", self.pointer_type.name(), self.pointer_endianness.name());
+        if self.pointer_base != 0 {
+            let sign = if self.pointer_base < 0 { "-" } else { "+" };
+            name.push_str(&format!(" {}{:#x}", sign, self.pointer_base.unsigned_abs()));
+        }
+        name
+    }
+
+    /// Go to the offset read at the cursor, plus the base.
+    pub fn follow_pointer(&mut self) {
+        let size = self.pointer_type.size();
+        let end = (self.cursor_index + size).min(self.buffer.len());
+        let bytes = self.buffer.slice(self.cursor_index, end);
+        let Some(value) = self.pointer_type.read_bits(&bytes, self.pointer_endianness) else {
+            self.show_error(format!("Not enough bytes for a {} pointer", self.pointer_type.name()));
+            return;
+        };
+        let target = value as i128 + self.pointer_base;
+        if target < 0 || target >= self.buffer.len() as i128 {
+            self.show_error(format!("Pointer {:#x} is out of the file ({})", value, self.pointer_name()));
+            return;
+        }
+        self.jumps.push_return(self.cursor_index);
+        self.jump_to(target as usize);
+    }
+
+    /// Go back to where the last followed pointer was read.
+    pub fn return_from_pointer(&mut self) {
+        match self.jumps.pop_return() {
+            Some(offset) => self.jump_to(offset.min(self.buffer.len().saturating_sub(1))),
+            None => self.show_error("No pointer to return from".to_string()),
+        }
+    }
+}
@@ -0,0 +1,17 @@
+use crossterm::event::KeyCode;
+use crate::editor::{Editor, Mode};
+impl Editor {
+    pub fn quit_inputs(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Char('y') => {
+                self.exit = true;
+                self.force_exit = true;
+            }
+            _ => {
+                self.mode = Mode::Normal;
+                self.refresh = true;
+            }
+        }
+
+    }
+}
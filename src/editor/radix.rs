@@ -0,0 +1,148 @@
+/// The base used to show and edit the bytes of the grid.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Radix {
+    Hex,
+    Binary,
+    Octal,
+    Decimal,
+}
+
+impl Radix {
+    pub fn next(&self) -> Radix {
+        match self {
+            Radix::Hex => Radix::Binary,
+            Radix::Binary => Radix::Octal,
+            Radix::Octal => Radix::Decimal,
+            Radix::Decimal => Radix::Hex,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Radix::Hex => "hexadecimal",
+            Radix::Binary => "binary",
+            Radix::Octal => "octal",
+            Radix::Decimal => "decimal",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Radix> {
+        match name {
+            "hex" | "hexadecimal" => Some(Radix::Hex),
+            "bin" | "binary" => Some(Radix::Binary),
+            "oct" | "octal" => Some(Radix::Octal),
+            "dec" | "decimal" => Some(Radix::Decimal),
+            _ => None,
+        }
+    }
+
+    fn base(&self) -> u32 {
+        match self {
+            Radix::Hex => 16,
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Decimal => 10,
+        }
+    }
+
+    /// Digits needed to write any byte.
+    pub fn digits(&self) -> usize {
+        match self {
+            Radix::Hex => 2,
+            Radix::Binary => 8,
+            Radix::Octal | Radix::Decimal => 3,
+        }
+    }
+
+    pub fn format(&self, byte: u8) -> String {
+        match self {
+            Radix::Hex => format!("{:02x}", byte),
+            Radix::Binary => format!("{:08b}", byte),
+            Radix::Octal => format!("{:03o}", byte),
+            Radix::Decimal => format!("{:03}", byte),
+        }
+    }
+
+    /// Value of a typed digit, if it is one of this base.
+    pub fn digit_value(&self, c: char) -> Option<u32> {
+        c.to_digit(self.base())
+    }
+
+    /// The byte with its digit at `index` (0 being the leftmost) set to
+    /// `value`, or None if the result is over 255. The digits after it are
+    /// cleared when they would make it over 255, so that typing 2 on 099
+    /// in decimal gives 200, on the way to 250.
+    pub fn set_digit(&self, byte: u8, index: usize, value: u32) -> Option<u8> {
+        let weight = self.base().pow((self.digits() - 1 - index) as u32);
+        let old = byte as u32 / weight % self.base();
+        let digit_set = byte as u32 - old * weight + value * weight;
+        u8::try_from(digit_set)
+            .or_else(|_| u8::try_from(digit_set - digit_set % weight))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RADIXES: [Radix; 4] = [Radix::Hex, Radix::Binary, Radix::Octal, Radix::Decimal];
+
+    // The digit replaced in the text of the byte, then the following
+    // digits cleared if the value is over 255
+    fn naive_set_digit(radix: Radix, byte: u8, index: usize, value: u32) -> Option<u8> {
+        let mut digits: Vec<char> = radix.format(byte).chars().collect();
+        digits[index] = char::from_digit(value, radix.base()).unwrap();
+        let parse = |digits: &[char]| u32::from_str_radix(&digits.iter().collect::<String>(), radix.base()).unwrap();
+        if parse(&digits) <= 255 {
+            return Some(parse(&digits) as u8);
+        }
+        for digit in digits[index + 1..].iter_mut() {
+            *digit = '0';
+        }
+        u8::try_from(parse(&digits)).ok()
+    }
+
+    #[test]
+    fn set_digit_as_the_text() {
+        for radix in RADIXES {
+            for byte in 0..=255 {
+                for index in 0..radix.digits() {
+                    for value in 0..radix.base() {
+                        assert_eq!(
+                            radix.set_digit(byte, index, value),
+                            naive_set_digit(radix, byte, index, value),
+                            "{} {} {} {}",
+                            radix.name(),
+                            byte,
+                            index,
+                            value
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_digit_examples() {
+        assert_eq!(Radix::Decimal.set_digit(99, 0, 2), Some(200));
+        assert_eq!(Radix::Decimal.set_digit(200, 1, 5), Some(250));
+        assert_eq!(Radix::Decimal.set_digit(250, 2, 9), None);
+        assert_eq!(Radix::Decimal.set_digit(0, 0, 3), None);
+        assert_eq!(Radix::Octal.set_digit(0o77, 0, 3), Some(0o377));
+        assert_eq!(Radix::Octal.set_digit(0, 0, 4), None);
+        assert_eq!(Radix::Hex.set_digit(0x12, 0, 0xa), Some(0xa2));
+        assert_eq!(Radix::Binary.set_digit(0, 0, 1), Some(0x80));
+    }
+
+    #[test]
+    fn names_and_formats() {
+        for radix in RADIXES {
+            assert!(Radix::from_name(radix.name()) == Some(radix));
+            assert_eq!(radix.format(255).len(), radix.digits());
+            assert_eq!(u8::from_str_radix(&radix.format(173), radix.base()), Ok(173));
+        }
+        assert!(Radix::Hex.next().next().next().next() == Radix::Hex);
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+
+use crossterm::event::KeyCode;
+use crate::editor::Editor;
+
+// Older yanks are forgotten
+const HISTORY_SIZE: usize = 10;
+// Bytes shown for each register by :registers
+const PREVIEW_LENGTH: usize = 4;
+
+/// The bytes copied or cut, kept in the named registers `a` to `z` and in
+/// the history of the last ones, `0` being the latest.
+#[derive(Default)]
+pub struct Registers {
+    named: BTreeMap<char, Vec<u8>>,
+    history: VecDeque<Vec<u8>>,
+}
+
+impl Registers {
+    /// Keep copied or cut bytes, in the history and in `register` if given.
+    /// An upper case register appends them to the lower case one.
+    pub fn store(&mut self, register: Option<char>, bytes: Vec<u8>) {
+        match register {
+            Some(name) if name.is_ascii_uppercase() => {
+                self.named.entry(name.to_ascii_lowercase()).or_default().extend_from_slice(&bytes);
+            }
+            Some(name) if name.is_ascii_lowercase() => {
+                self.named.insert(name, bytes.clone());
+            }
+            _ => {}
+        }
+        self.history.push_front(bytes);
+        self.history.truncate(HISTORY_SIZE);
+    }
+
+    /// The bytes of a register, the last copied or cut ones without one.
+    pub fn get(&self, register: Option<char>) -> Option<&[u8]> {
+        let bytes = match register {
+            None | Some('"') => self.history.front(),
+            Some(c @ '0'..='9') => self.history.get(c as usize - '0' as usize),
+            Some(name) => self.named.get(&name.to_ascii_lowercase()),
+        };
+        bytes.map(Vec::as_slice)
+    }
+
+    // The history first, then the named registers
+    fn iter(&self) -> impl Iterator<Item = (char, &Vec<u8>)> + '_ {
+        let history = self.history.iter().enumerate().map(|(i, bytes)| ((b'0' + i as u8) as char, bytes));
+        history.chain(self.named.iter().map(|(name, bytes)| (*name, bytes)))
+    }
+}
+
+pub fn is_register_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '"'
+}
+
+impl Editor {
+    /// Use the same registers as `other`, to paste in a file what was
+    /// copied in another one.
+    pub fn share_registers(&mut self, other: &Editor) {
+        self.registers = Rc::clone(&other.registers);
+    }
+
+    /// Choose the register of the next copy, cut or paste, typed after '"'.
+    pub fn select_register(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Char(name) if is_register_name(name) => self.register = Some(name),
+            KeyCode::Char(name) => self.show_error(format!("Invalid register '{}'", name)),
+            // <ESC> cancels it
+            _ => {}
+        }
+        self.refresh = true;
+    }
+
+    /// Keep copied or cut bytes in the register chosen for them.
+    pub fn store_register(&mut self, bytes: Vec<u8>) {
+        let register = self.register.take();
+        self.registers.borrow_mut().store(register, bytes);
+    }
+
+    /// The bytes to paste from the register chosen for them, None with an
+    /// error shown if it's empty.
+    pub fn take_register(&mut self) -> Option<Vec<u8>> {
+        let register = self.register.take();
+        let bytes = self.registers.borrow().get(register).filter(|bytes| !bytes.is_empty()).map(<[u8]>::to_vec);
+        if bytes.is_none() {
+            match register {
+                Some(name) if name != '"' => self.show_error(format!("Register {} is empty", name)),
+                _ => self.show_error("Nothing to paste, copy bytes first (y, x or v)".to_string()),
+            }
+        }
+        bytes
+    }
+
+    /// Show the beginning of each register.
+    pub fn show_registers(&mut self) {
+        let texts: Vec<String> = self.registers
+            .borrow()
+            .iter()
+            .map(|(name, bytes)| {
+                let hex: Vec<String> = bytes.iter().take(PREVIEW_LENGTH).map(|byte| format!("{:02x}", byte)).collect();
+                let more = if bytes.len() > PREVIEW_LENGTH { "..." } else { "" };
+                format!("\"{} {}{} ({})", name, hex.join(""), more, bytes.len())
+            })
+            .collect();
+        if texts.is_empty() {
+            self.show_info("No register, y or x fills them".to_string());
+        } else {
+            self.show_info(texts.join("  "));
+        }
+    }
+}
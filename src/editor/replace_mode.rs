@@ -0,0 +1,250 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use crossterm::event::KeyCode;
+use crate::editor::{Editor, Mode};
+use crate::editor::pattern::Pattern;
+use crate::editor::searcher::Searcher;
+
+/// A replacement command: `FIND/REPLACEMENT/FLAGS`.
+///
+/// FIND and REPLACEMENT are hex bytes (`?` wildcards are allowed in FIND)
+/// or "quoted text", and can mix both. The flags are `g` to replace all the
+/// occurrences instead of the next one, and `c` to confirm each replacement.
+pub struct Replacement {
+    find: Pattern,
+    bytes: Vec<u8>,
+    all: bool,
+    confirm: bool,
+}
+
+impl Replacement {
+    pub fn parse(command: &str) -> Result<Replacement, String> {
+        let mut chars = command.chars().peekable();
+        let find = parse_term(&mut chars)?;
+        if find.is_empty() {
+            return Err("Nothing to find".to_string());
+        }
+        let replacement = parse_term(&mut chars)?;
+        if replacement.has_wildcards() {
+            return Err("No wildcard allowed in the replacement".to_string());
+        }
+
+        let mut all = false;
+        let mut confirm = false;
+        for c in chars {
+            match c {
+                'g' => all = true,
+                'c' => confirm = true,
+                ' ' => {}
+                _ => return Err(format!("Unknown flag '{}'", c)),
+            }
+        }
+        Ok(Replacement {
+            find,
+            bytes: replacement.bytes().to_vec(),
+            all,
+            confirm,
+        })
+    }
+}
+
+/// Read hex bytes and quoted text up to the next '/'.
+pub fn parse_term(chars: &mut Peekable<Chars>) -> Result<Pattern, String> {
+    let mut pattern = Pattern::default();
+    let mut nibble_index = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' => break,
+            ' ' => {}
+            '"' if nibble_index == 0 => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => text.extend(chars.next()),
+                        Some(c) => text.push(c),
+                        None => return Err("Missing closing '\"'".to_string()),
+                    }
+                }
+                pattern.push_bytes(text.as_bytes());
+            }
+            '?' => {
+                pattern.push_nibble(None, nibble_index);
+                nibble_index = 1 - nibble_index;
+            }
+            _ => match c.to_digit(16) {
+                Some(value) => {
+                    pattern.push_nibble(Some(value as u8), nibble_index);
+                    nibble_index = 1 - nibble_index;
+                }
+                None => return Err(format!("Unexpected '{}'", c)),
+            },
+        }
+    }
+    if nibble_index != 0 {
+        return Err("Incomplete byte".to_string());
+    }
+    Ok(pattern)
+}
+
+/// A replacement waiting for a confirmation of each occurrence.
+pub struct ReplaceState {
+    bytes: Vec<u8>,
+    find_length: usize,
+    // Positions of the occurrences, before any replacement
+    matches: Vec<usize>,
+    next: usize,
+    // Shift of the positions due to the replacements already done
+    delta: isize,
+    count: usize,
+}
+
+impl ReplaceState {
+    pub fn find_length(&self) -> usize {
+        self.find_length
+    }
+
+    fn position(&self) -> usize {
+        (self.matches[self.next] as isize + self.delta) as usize
+    }
+}
+
+impl Editor {
+    pub fn replace_inputs(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Esc => {
+                self.replace_range = None;
+                self.mode = Mode::Normal;
+                self.refresh = true;
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.refresh = true;
+            }
+            KeyCode::Enter => {
+                let command = self.input.clone();
+                self.mode = Mode::Normal;
+                self.replace(&command);
+                self.refresh = true;
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                self.refresh = true;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn replace_confirm_inputs(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Char('y') => {
+                self.replace_next(true);
+            }
+            KeyCode::Char('n') => {
+                self.replace_next(false);
+            }
+            KeyCode::Char('a') => {
+                while self.replace_state.is_some() {
+                    self.replace_next(true);
+                }
+            }
+            KeyCode::Char('q') | KeyCode::Esc => {
+                self.end_replace();
+            }
+            _ => {}
+        }
+    }
+
+    /// Run a replacement command, in the selection if one was made.
+    pub fn replace(&mut self, command: &str) {
+        let replacement = match Replacement::parse(command) {
+            Ok(replacement) => replacement,
+            Err(e) => {
+                self.replace_range = None;
+                self.show_error(e);
+                return;
+            }
+        };
+
+        // Without a selection nor the 'g' flag, the next occurrence is replaced
+        let (start, end) = match self.replace_range.take() {
+            Some(range) => range,
+            None if replacement.all || replacement.confirm => (0, self.buffer.len()),
+            None => (self.cursor_index, self.buffer.len()),
+        };
+        let find_length = replacement.find.len();
+        let searcher = Searcher::new(replacement.find);
+        let mut matches = self.find_in_range(&searcher, start, end);
+
+        // Overlapping occurrences can't all be replaced
+        let mut last_end = 0;
+        matches.retain(|m| {
+            let keep = *m >= last_end;
+            if keep {
+                last_end = m + find_length;
+            }
+            keep
+        });
+        if !replacement.all && !replacement.confirm {
+            matches.truncate(1);
+        }
+        if matches.is_empty() {
+            self.show_info("Pattern not found".to_string());
+            return;
+        }
+
+        self.history.begin_batch();
+        self.replace_state = Some(ReplaceState {
+            bytes: replacement.bytes,
+            find_length,
+            matches,
+            next: 0,
+            delta: 0,
+            count: 0,
+        });
+        if replacement.confirm {
+            self.cursor_index = self.replace_state.as_ref().map_or(0, |state| state.position());
+            self.mode = Mode::ReplaceConfirm;
+            self.refresh = true;
+        } else {
+            while self.replace_state.is_some() {
+                self.replace_next(true);
+            }
+        }
+    }
+
+    // Replace the current occurrence, or skip it, and go to the next one
+    fn replace_next(&mut self, replace: bool) {
+        let Some(mut state) = self.replace_state.take() else {
+            return;
+        };
+        let position = state.position();
+        if replace {
+            self.replace_bytes(position, state.find_length, &state.bytes, false);
+            state.delta += state.bytes.len() as isize - state.find_length as isize;
+            state.count += 1;
+        }
+        state.next += 1;
+        self.cursor_index = position;
+
+        let done = state.next == state.matches.len();
+        if !done {
+            self.cursor_index = state.position();
+        }
+        self.replace_state = Some(state);
+        if done {
+            self.end_replace();
+        }
+        self.refresh = true;
+    }
+
+    fn end_replace(&mut self) {
+        if let Some(state) = self.replace_state.take() {
+            self.show_info(format!("{} replacement(s)", state.count));
+        }
+        self.history.end_batch();
+        self.mode = Mode::Normal;
+        self.refresh = true;
+    }
+}
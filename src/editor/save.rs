@@ -0,0 +1,81 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::Path;
+use std::process;
+
+use crate::editor::buffer::Buffer;
+
+// Names tried for the temporary file, when some are already taken
+const TEMP_ATTEMPTS: usize = 100;
+
+/// Write `buffer` to `file_name` without ever leaving a partially written file.
+///
+/// The content goes to a temporary file in the same directory, which is synced
+/// to the disk and then renamed over the original file. With `backup`, the
+/// previous version of the file is kept as `file_name~`.
+pub fn save(buffer: &Buffer, file_name: &str, backup: bool) -> io::Result<()> {
+    let path = Path::new(file_name);
+    let (temp_file, temp_name) = create_temp(file_name, path)?;
+
+    let result = write_temp(buffer, temp_file, path, &temp_name);
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_name);
+        return result;
+    }
+
+    if backup && path.exists() {
+        let backup_name = format!("{}~", file_name);
+        let _ = fs::remove_file(&backup_name);
+        // A hard link keeps the old content without copying it,
+        // the copy is only needed on file systems without links
+        if fs::hard_link(path, &backup_name).is_err() {
+            fs::copy(path, &backup_name)?;
+        }
+    }
+
+    fs::rename(&temp_name, path)?;
+
+    // Sync the directory too, so that the rename itself is on the disk
+    if let Some(directory) = path.parent() {
+        let directory = if directory.as_os_str().is_empty() { Path::new(".") } else { directory };
+        if let Ok(d) = File::open(directory) {
+            let _ = d.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// A new file next to the original one, never an existing file or a link
+// left there, created with the permissions of the original one so that
+// its content is never readable by more users
+fn create_temp(file_name: &str, path: &Path) -> io::Result<(File, String)> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if let Ok(metadata) = fs::metadata(path) {
+        options.mode(metadata.permissions().mode());
+    }
+    for attempt in 0..TEMP_ATTEMPTS {
+        let temp_name = format!("{}.rhexed-tmp{}-{}", file_name, process::id(), attempt);
+        match options.open(&temp_name) {
+            Ok(file) => return Ok((file, temp_name)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "no free name for the temporary file"))
+}
+
+fn write_temp(buffer: &Buffer, file: File, path: &Path, temp_name: &str) -> io::Result<()> {
+    let mut f = BufWriter::new(file);
+    buffer.write_to(&mut f)?;
+    let f = f.into_inner().map_err(|e| e.into_error())?;
+    f.sync_all()?;
+    // The umask may have removed some of them
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(temp_name, metadata.permissions())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,158 @@
+use std::fmt::Display;
+use std::io::{self, Stdout, Write};
+
+use crossterm::{
+    cursor,
+    style::{Attribute, Color, Colors, Print, SetAttribute, SetColors, StyledContent},
+    terminal::{self, Clear, ClearType},
+    QueueableCommand,
+};
+
+use crate::editor::charset;
+
+#[derive(Clone, Copy, PartialEq)]
+struct Cell {
+    // '\0' for the right half of a wide char
+    c: char,
+    fg: Color,
+    bg: Color,
+    underlined: bool,
+}
+
+const BLANK: Cell = Cell {
+    c: ' ',
+    fg: Color::Reset,
+    bg: Color::Reset,
+    underlined: false,
+};
+
+/// The content of the terminal, drawn as with a cursor, before being shown.
+#[derive(Clone)]
+pub struct Frame {
+    width: u16,
+    height: u16,
+    cells: Vec<Cell>,
+    x: u16,
+    y: u16,
+}
+
+impl Frame {
+    pub fn new(width: u16, height: u16) -> Frame {
+        Frame {
+            width,
+            height,
+            cells: vec![BLANK; width as usize * height as usize],
+            x: 0,
+            y: 0,
+        }
+    }
+
+    pub fn move_to(&mut self, x: u16, y: u16) {
+        self.x = x;
+        self.y = y;
+    }
+
+    pub fn move_to_column(&mut self, x: u16) {
+        self.x = x;
+    }
+
+    pub fn move_to_next_line(&mut self, lines: u16) {
+        self.x = 0;
+        self.y = self.y.saturating_add(lines);
+    }
+
+    /// Write a text with its style at the cursor, which moves after it.
+    /// What is out of the frame is lost.
+    pub fn print<D: Display>(&mut self, content: StyledContent<D>) {
+        let style = content.style();
+        let mut cell = Cell {
+            c: ' ',
+            fg: style.foreground_color.unwrap_or(Color::Reset),
+            bg: style.background_color.unwrap_or(Color::Reset),
+            underlined: style.attributes.has(Attribute::Underlined),
+        };
+        for c in content.content().to_string().chars() {
+            let wide = charset::is_wide(c);
+            cell.c = if wide && self.x + 1 >= self.width { ' ' } else { c };
+            self.set(self.x, self.y, cell);
+            self.x = self.x.saturating_add(1);
+            if wide {
+                self.set(self.x, self.y, Cell { c: '\0', ..cell });
+                self.x = self.x.saturating_add(1);
+            }
+        }
+    }
+
+    fn set(&mut self, x: u16, y: u16, cell: Cell) {
+        if x < self.width && y < self.height {
+            self.cells[y as usize * self.width as usize + x as usize] = cell;
+        }
+    }
+}
+
+/// The terminal, where only the cells changed since the previous frame
+/// are written.
+pub struct Screen {
+    stdout: Stdout,
+    previous: Option<Frame>,
+}
+
+impl Screen {
+    pub fn new(stdout: Stdout) -> Screen {
+        Screen { stdout, previous: None }
+    }
+
+    /// A frame filling the terminal.
+    pub fn frame(&self) -> io::Result<Frame> {
+        let (width, height) = terminal::size()?;
+        Ok(Frame::new(width, height))
+    }
+
+    /// Write the whole next frame, when the terminal may have been changed
+    /// by something else.
+    pub fn invalidate(&mut self) {
+        self.previous = None;
+    }
+
+    pub fn show(&mut self, frame: Frame) -> io::Result<()> {
+        // A new or resized terminal is cleared, then drawn from scratch
+        let previous = match self.previous.take() {
+            Some(previous) if previous.width == frame.width && previous.height == frame.height => previous,
+            _ => {
+                self.stdout.queue(Clear(ClearType::All))?;
+                Frame::new(frame.width, frame.height)
+            }
+        };
+
+        let mut position: Option<(u16, u16)> = None;
+        let mut style: Option<(Color, Color, bool)> = None;
+        for y in 0..frame.height {
+            for x in 0..frame.width {
+                let index = y as usize * frame.width as usize + x as usize;
+                let cell = frame.cells[index];
+                if cell == previous.cells[index] || cell.c == '\0' {
+                    continue;
+                }
+                if position != Some((x, y)) {
+                    self.stdout.queue(cursor::MoveTo(x, y))?;
+                }
+                if style != Some((cell.fg, cell.bg, cell.underlined)) {
+                    let attribute = if cell.underlined { Attribute::Underlined } else { Attribute::NoUnderline };
+                    self.stdout
+                        .queue(SetColors(Colors::new(cell.fg, cell.bg)))?
+                        .queue(SetAttribute(attribute))?;
+                    style = Some((cell.fg, cell.bg, cell.underlined));
+                }
+                self.stdout.queue(Print(cell.c))?;
+                let width = if charset::is_wide(cell.c) { 2 } else { 1 };
+                position = Some((x + width, y));
+            }
+        }
+        self.stdout
+            .queue(SetColors(Colors::new(Color::Reset, Color::Reset)))?
+            .queue(SetAttribute(Attribute::NoUnderline))?
+            .flush()?;
+        self.previous = Some(frame);
+        Ok(())
+    }
+}
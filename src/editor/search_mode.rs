@@ -1,6 +1,12 @@
 
 use crossterm::event::KeyCode;
-use crate::editor::{Editor, Mode, write_nibble};
+use std::cmp;
+
+use crate::editor::{Editor, Mode};
+use crate::editor::pattern::Pattern;
+use crate::editor::searcher::{SearchJob, Searcher};
+
+const SEARCH_CHUNK: usize = 4 * 1024 * 1024;
 
 impl Editor {
     pub fn search_inputs(&mut self, key_code: KeyCode) {
@@ -15,67 +21,116 @@ impl Editor {
                 self.search_pattern.pop();
                 self.refresh = true;
             }
+            // The prompt stays open for the missing digit
+            KeyCode::Enter if self.nibble_index != 0 => {
+                self.show_error("Incomplete byte, type its second digit (or ? for any)".to_string());
+            }
             KeyCode::Enter => {
-                self.nibble_index = 0;
                 if !self.search_pattern.is_empty() {
                     self.search();
                 }
                 self.mode = Mode::Normal;
-                if !self.search_result.is_empty() {
-                    self.cursor_index = self.search_result[0] as usize;
-                }
-                
                 self.refresh = true;
             }
             _ => {}
         }
 
         if let KeyCode::Char(k) = key_code {
-            let mut valid_entry = false;
-            let mut value = 0;
-            if k as u8 >= 48 && k as u8 <= 57 {
-                value = k as u8 - 48;
-                valid_entry = true;
-
-            } else if k as u8 >= 97 && k as u8 <= 102 {
-                value = k as u8 - 87;
-                valid_entry = true;
-            }
-            if valid_entry {
-                if self.nibble_index == 0 {
-                    self.search_pattern.push(0);
-                }
-                let pattern_length = self.search_pattern.len();
-                write_nibble(
-                    &mut self.search_pattern,
-                    pattern_length - 1,
-                    value,
-                    self.nibble_index,
-                );
+            // '?' is a wildcard nibble
+            let value = match k {
+                '0'..='9' => Some(Some(k as u8 - 48)),
+                'a'..='f' => Some(Some(k as u8 - 87)),
+                '?' => Some(None),
+                _ => None,
+            };
+            if let Some(value) = value {
+                self.search_pattern.push_nibble(value, self.nibble_index);
                 self.nibble_index += 1;
                 if self.nibble_index > 1 {
                     self.nibble_index = 0;
                 }
                 self.refresh = true;
             }
-
-
         }
     }
 
-    fn search(&mut self) {
+    /// Start searching the current pattern. The search itself is done by
+    /// `search_step`, so that it can show its progress and be cancelled.
+    pub fn search(&mut self) {
+        self.search_any(vec![self.search_pattern.clone()]);
+    }
 
+    /// Start searching several patterns at once, of the same length.
+    pub fn search_any(&mut self, patterns: Vec<Pattern>) {
         self.search_result.clear();
-        for (i,_) in self.buffer.iter().enumerate() {
-            let mut flag = true;
-            'pat: for (j,_) in self.search_pattern.iter().enumerate() {
-                if self.buffer[i + j] != self.search_pattern[j] { 
-                    flag = false;
-                    break 'pat;
-                }
+        self.search_job = Some(SearchJob {
+            searchers: patterns.into_iter().map(Searcher::new).collect(),
+            position: 0,
+        });
+        self.refresh = true;
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.search_job.is_some()
+    }
+
+    /// Search the next chunk of the file.
+    pub fn search_step(&mut self) {
+        let progress = self.search_progress();
+        let Some(mut job) = self.search_job.take() else {
+            return;
+        };
+
+        let start = job.position;
+        let pattern_length = job.searchers.iter().map(|s| s.pattern_len()).max().unwrap_or(1);
+        let end = cmp::min(start + SEARCH_CHUNK + pattern_length - 1, self.buffer.len());
+        let mut found: Vec<usize> = Vec::new();
+        for searcher in job.searchers.iter() {
+            found.extend(self.find_in_range(searcher, start, end));
+        }
+        found.sort_unstable();
+        found.dedup();
+        self.search_result.extend(found);
+        job.position += SEARCH_CHUNK;
+
+        if end == self.buffer.len() {
+            if !self.search_result.is_empty() {
+                self.jump_to(self.search_result[0]);
+            }
+            self.refresh = true;
+        } else {
+            self.search_job = Some(job);
+            if self.search_progress() != progress {
+                self.refresh = true;
             }
-            if flag == true {self.search_result.push(i as u32)}
         }
+    }
+
+    /// Positions of all the matches lying in `start..end`.
+    pub fn find_in_range(&self, searcher: &Searcher, start: usize, end: usize) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut position = start;
+        while position < end {
+            // Each chunk overlaps the next one by the length of the pattern,
+            // to find the matches across chunks
+            let chunk_end = cmp::min(position + SEARCH_CHUNK + searcher.pattern_len() - 1, end);
+            let chunk = self.buffer.slice(position, chunk_end);
+            searcher.find_all(&chunk, |i| found.push(position + i));
+            position += SEARCH_CHUNK;
+        }
+        found
+    }
 
+    pub fn cancel_search(&mut self) {
+        self.search_job = None;
+        self.refresh = true;
+    }
+
+    /// Progress of the running search, in percent.
+    pub fn search_progress(&self) -> usize {
+        match &self.search_job {
+            Some(job) => cmp::min(job.position, self.buffer.len()) * 100 / cmp::max(self.buffer.len(), 1),
+            None => 100,
+        }
     }
 }
@@ -0,0 +1,134 @@
+use crate::editor::pattern::Pattern;
+
+/// Boyer-Moore-Horspool search of a pattern, wildcards included.
+///
+/// After each try, the window moves forward as far as possible: up to the
+/// last position of the pattern which could match the byte currently under
+/// the end of the window.
+pub struct Searcher {
+    pattern: Pattern,
+    shift: [usize; 256],
+}
+
+impl Searcher {
+    pub fn new(pattern: Pattern) -> Searcher {
+        let length = pattern.len();
+        let mut shift = [length; 256];
+        for j in 0..length.saturating_sub(1) {
+            for (byte, s) in shift.iter_mut().enumerate() {
+                if pattern.matches_at(j, byte as u8) {
+                    *s = length - 1 - j;
+                }
+            }
+        }
+        Searcher { pattern, shift }
+    }
+
+    pub fn pattern_len(&self) -> usize {
+        self.pattern.len()
+    }
+
+    /// Call `found` with the position of every match in `haystack`.
+    pub fn find_all(&self, haystack: &[u8], mut found: impl FnMut(usize)) {
+        let length = self.pattern.len();
+        if length == 0 {
+            return;
+        }
+        let mut i = 0;
+        while i + length <= haystack.len() {
+            let last = haystack[i + length - 1];
+            if self.pattern.matches_at(length - 1, last) && self.pattern.matches(&haystack[i..i + length]) {
+                found(i);
+            }
+            i += self.shift[last as usize];
+        }
+    }
+}
+
+/// A search running over the whole buffer, one chunk at a time.
+/// Several patterns can be searched at once.
+pub struct SearchJob {
+    pub searchers: Vec<Searcher>,
+    pub position: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Same sequence of numbers in every run
+    fn random(seed: &mut u64) -> usize {
+        *seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (*seed >> 33) as usize
+    }
+
+    fn find_all(pattern: &Pattern, haystack: &[u8]) -> Vec<usize> {
+        let mut results = Vec::new();
+        Searcher::new(pattern.clone()).find_all(haystack, |i| results.push(i));
+        results
+    }
+
+    // Every window compared to the pattern
+    fn naive_find_all(pattern: &Pattern, haystack: &[u8]) -> Vec<usize> {
+        if pattern.is_empty() {
+            return Vec::new();
+        }
+        haystack
+            .windows(pattern.len())
+            .enumerate()
+            .filter(|(_, window)| pattern.matches(window))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn finds_overlapping_results() {
+        let pattern = Pattern::from_bytes(b"aa".to_vec());
+        assert_eq!(find_all(&pattern, b"aaaa"), [0, 1, 2]);
+        assert_eq!(find_all(&pattern, b"a"), []);
+        assert_eq!(find_all(&Pattern::default(), b"aaaa"), []);
+    }
+
+    #[test]
+    fn wildcard_nibbles() {
+        // 8? ?f
+        let mut pattern = Pattern::default();
+        pattern.push_nibble(Some(8), 0);
+        pattern.push_nibble(None, 1);
+        pattern.push_nibble(None, 0);
+        pattern.push_nibble(Some(0xf), 1);
+        assert_eq!(find_all(&pattern, &[0x80, 0x0f, 0x8f, 0x8f, 0x7f, 0x1f]), [0, 2, 3]);
+        assert_eq!(pattern.to_string(), " 8? ?f");
+    }
+
+    #[test]
+    fn ignored_case() {
+        let mut pattern = Pattern::from_bytes(b"PnG".to_vec());
+        for index in 0..pattern.len() {
+            pattern.ignore_case_at(index);
+        }
+        assert_eq!(find_all(&pattern, b"png PNG pNg pmg"), [0, 4, 8]);
+    }
+
+    #[test]
+    fn random_patterns_as_the_naive_search() {
+        let mut seed = 4;
+        for _ in 0..300 {
+            // Few different bytes, to have many results
+            let haystack: Vec<u8> = (0..random(&mut seed) % 300).map(|_| [0x00, 0x0f, 0xf0, 0xff, 0x5a][random(&mut seed) % 5]).collect();
+            let mut pattern = Pattern::default();
+            for _ in 0..1 + random(&mut seed) % 5 {
+                for nibble_index in 0..2 {
+                    let value = match random(&mut seed) % 4 {
+                        0 => None,
+                        1 => Some(0),
+                        2 => Some(0xf),
+                        _ => Some((random(&mut seed) % 16) as u8),
+                    };
+                    pattern.push_nibble(value, nibble_index);
+                }
+            }
+            assert_eq!(find_all(&pattern, &haystack), naive_find_all(&pattern, &haystack), "pattern{}", pattern);
+        }
+    }
+}
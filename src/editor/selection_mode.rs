@@ -1,41 +1,105 @@
+use std::cmp;
+
 use crate::editor::{Editor, Mode};
 use crossterm::event::KeyCode;
 
 impl Editor {
     pub fn selection_inputs(&mut self, key_code: KeyCode) {
+        if self.pending.take() == Some('"') {
+            self.select_register(key_code);
+            return;
+        }
+        if self.motion_inputs(key_code) {
+            return;
+        }
+        let (start, end) = self.selection();
         match key_code {
-            KeyCode::Esc => {
+            KeyCode::Esc | KeyCode::Char('v') => {
                 self.mode = Mode::Normal;
                 self.refresh = true;
             }
-            KeyCode::Char('q') => self.exit = true,
-            KeyCode::Char('h') | KeyCode::Left => {
-                if self.cursor_index > 0 {
-                    self.cursor_index -= 1;
-                    self.refresh = true;
-                }
+            KeyCode::Char('"') => {
+                self.pending = Some('"');
+                self.refresh = true;
             }
-            KeyCode::Char('j') | KeyCode::Down => {
-                if self.cursor_index < self.buffer.len() - 16 {
-                    self.cursor_index += 16;
-                    self.refresh = true;
-                }
+            KeyCode::Char(':') => {
+                self.input.clear();
+                self.command_history_index = self.command_history.len();
+                self.command_range = Some((start, end));
+                self.mode = Mode::Command;
+                self.refresh = true;
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                if self.cursor_index >= 16 {
-                    self.cursor_index -= 16;
-                    self.refresh = true;
-                }
+            // The bytes to fill the selection with are typed on the command line
+            KeyCode::Char('f') => {
+                self.input = "fill ".to_string();
+                self.command_history_index = self.command_history.len();
+                self.command_range = Some((start, end));
+                self.mode = Mode::Command;
+                self.refresh = true;
             }
-            KeyCode::Char('l') | KeyCode::Right => {
-                if self.cursor_index < self.buffer.len() - 1 {
-                    self.cursor_index += 1;
-                    self.refresh = true;
+            KeyCode::Char('S') => {
+                self.input.clear();
+                self.replace_range = Some((start, end));
+                self.mode = Mode::Replace;
+                self.refresh = true;
+            }
+            KeyCode::Char('J') => {
+                self.input.clear();
+                self.jump_return = Mode::Selection;
+                self.mode = Mode::Jump;
+                self.refresh = true;
+            }
+            // The other end of the selection
+            KeyCode::Char('o') => {
+                (self.cursor_start, self.cursor_index) = (self.cursor_index, self.cursor_start);
+                self.refresh = true;
+            }
+            KeyCode::Char('y') => {
+                self.store_register(self.buffer.slice(start, end));
+                self.end_selection(start);
+                self.show_info(format!("{} byte(s) copied", end - start));
+            }
+            KeyCode::Char('x') | KeyCode::Char('d') => {
+                self.store_register(self.buffer.slice(start, end));
+                self.replace_bytes(start, end - start, &[], false);
+                self.end_selection(start);
+            }
+            // The selection is replaced by the register, whatever their sizes
+            KeyCode::Char('p') => {
+                if let Some(bytes) = self.take_register() {
+                    self.replace_bytes(start, end - start, &bytes, false);
+                    self.end_selection(start);
                 }
             }
             _ => {}
         }
+        if self.mode != Mode::Selection && self.mode != Mode::Jump {
+            self.register = None;
+        }
+    }
 
+    /// The selected bytes, from `start` to `end` excluded, whichever way the
+    /// selection was made.
+    pub fn selection(&self) -> (usize, usize) {
+        let start = cmp::min(self.cursor_start, self.cursor_index);
+        let end = cmp::max(self.cursor_start, self.cursor_index) + 1;
+        (start, cmp::min(end, self.buffer.len()))
     }
 
+    /// Whether the byte at `offset` is shown as selected.
+    pub fn is_selected(&self, offset: usize) -> bool {
+        // The selection stays visible while typing where to jump
+        let selecting = self.mode == Mode::Selection || (self.mode == Mode::Jump && self.jump_return == Mode::Selection);
+        if !selecting {
+            return false;
+        }
+        let (start, end) = self.selection();
+        offset >= start && offset < end
+    }
+
+    fn end_selection(&mut self, cursor: usize) {
+        self.cursor_index = cursor;
+        self.mode = Mode::Normal;
+        self.refresh = true;
+    }
 }
@@ -0,0 +1,99 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::editor::{Editor, Mode};
+use crate::editor::pattern::Pattern;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TextEncoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl TextEncoding {
+    pub fn name(&self) -> &str {
+        match self {
+            TextEncoding::Utf8 => "utf-8",
+            TextEncoding::Utf16Le => "utf-16le",
+            TextEncoding::Utf16Be => "utf-16be",
+        }
+    }
+
+    fn next(&self) -> TextEncoding {
+        match self {
+            TextEncoding::Utf8 => TextEncoding::Utf16Le,
+            TextEncoding::Utf16Le => TextEncoding::Utf16Be,
+            TextEncoding::Utf16Be => TextEncoding::Utf8,
+        }
+    }
+
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self {
+            TextEncoding::Utf8 => text.as_bytes().to_vec(),
+            TextEncoding::Utf16Le => text.encode_utf16().flat_map(|c| c.to_le_bytes()).collect(),
+            TextEncoding::Utf16Be => text.encode_utf16().flat_map(|c| c.to_be_bytes()).collect(),
+        }
+    }
+}
+
+impl Editor {
+    pub fn text_search_inputs(&mut self, key_event: KeyEvent) {
+        if key_event.modifiers.contains(KeyModifiers::CONTROL) {
+            match key_event.code {
+                // Switch between case sensitive and insensitive search
+                KeyCode::Char('t') => {
+                    self.search_ignore_case = !self.search_ignore_case;
+                    self.refresh = true;
+                }
+                // Cycle through the encodings of the searched text
+                KeyCode::Char('e') => {
+                    self.search_encoding = self.search_encoding.next();
+                    self.refresh = true;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        match key_event.code {
+            KeyCode::Esc => {
+                self.mode = Mode::Normal;
+                self.refresh = true;
+            }
+            KeyCode::Backspace => {
+                self.search_text.pop();
+                self.refresh = true;
+            }
+            KeyCode::Enter => {
+                self.search_pattern = self.text_pattern();
+                if !self.search_pattern.is_empty() {
+                    self.search();
+                }
+                self.mode = Mode::Normal;
+                self.refresh = true;
+            }
+            KeyCode::Char(c) => {
+                self.search_text.push(c);
+                self.refresh = true;
+            }
+            _ => {}
+        }
+    }
+
+    // The text searched, encoded char by char so that only the ASCII letters
+    // match both cases: the bytes of the other chars can look like letters
+    fn text_pattern(&self) -> Pattern {
+        let mut pattern = Pattern::default();
+        for c in self.search_text.chars() {
+            let bytes = self.search_encoding.encode(c.encode_utf8(&mut [0; 4]));
+            let start = pattern.len();
+            pattern.push_bytes(&bytes);
+            if self.search_ignore_case && c.is_ascii_alphabetic() {
+                // Its code, next to a 0 in utf-16
+                if let Some(index) = bytes.iter().position(|byte| *byte == c as u8) {
+                    pattern.ignore_case_at(start + index);
+                }
+            }
+        }
+        pattern
+    }
+}
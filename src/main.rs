@@ -1,72 +1,170 @@
+
 use std::cmp;
 use std::env;
 use std::io;
+use std::panic;
+use std::process;
+use std::thread;
+use std::time::Duration;
 
 mod editor;
 use crossterm::terminal;
-use editor::Editor;
+use editor::{Editor, Screen};
 
 use crossterm::{
     cursor,
-    event::{read, Event, KeyCode},
+    event::{poll, read, Event, KeyCode, KeyModifiers},
     terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType},
     ExecutableCommand,
 };
 
+const USAGE: &str = "Usage: rhexed [-b | --backup] [-m | --macros] [--] FILE...";
+
+// Put the terminal back in its normal state when dropped,
+// whether the editor quits normally, with an error or a panic
+struct TerminalGuard;
 
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        // On a panic, the hook has already done it before printing the message
+        if !thread::panicking() {
+            restore_terminal();
+        }
+    }
+}
+
+fn restore_terminal() {
+    let mut stdout = io::stdout();
+    let _ = stdout.execute(Clear(ClearType::All));
+    let _ = stdout.execute(cursor::Show);
+    let _ = stdout.execute(cursor::MoveTo(0, 0));
+    let _ = stdout.execute(terminal::EnableLineWrap);
+    let _ = disable_raw_mode();
+}
 
 fn main() -> io::Result<()> {
     let mut stdout = io::stdout();
 
     let mut show_title = true;
-    let args: Vec<String> = env::args().collect();
+    let mut backup = false;
+    let mut keep_macros = false;
+    let mut file_names: Vec<String> = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-b" | "--backup" => backup = true,
+            "-m" | "--macros" => keep_macros = true,
+            // The next arguments are files, even if they start with '-'
+            "--" => file_names.extend(args.by_ref()),
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown option {}\n{}", arg, USAGE);
+                process::exit(1);
+            }
+            _ => file_names.push(arg),
+        }
+    }
+    if file_names.is_empty() {
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+
+    // A file that can't be opened is reported, and the others are still edited
     let mut editors: Vec<Editor> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
     let mut current_editor = 0;
-    for file_number in 1..args.len() {
-        editors.push(Editor::new(&args[file_number], file_number - 1));
+    for file_name in &file_names {
+        match Editor::new(file_name, editors.len()) {
+            Ok(mut editor) => {
+                editor.backup = backup;
+                editor.keep_macros = keep_macros;
+                // The macros are read once, for all the files
+                if let Some(first) = editors.first() {
+                    editor.share_registers(first);
+                    editor.share_macros(first);
+                } else if keep_macros {
+                    editor.load_macros();
+                }
+                editors.push(editor);
+            }
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+    if editors.is_empty() {
+        for error in errors {
+            eprintln!("{}", error);
+        }
+        process::exit(1);
     }
+    if !errors.is_empty() {
+        editors[0].show_error(errors.join(" - "));
+    }
+
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        default_hook(info);
+    }));
+    let _guard = TerminalGuard;
 
     let _ = enable_raw_mode();
     stdout.execute(terminal::DisableLineWrap)?;
-    editors[current_editor].render(&mut stdout, show_title)?;
-    while !editors[current_editor].exit {
+    stdout.execute(cursor::Hide)?;
+    let mut screen = Screen::new(stdout);
+    editors[current_editor].render(&mut screen, show_title)?;
+    loop {
+        // A running search goes on between the keys, so that it can be cancelled
+        if editors[current_editor].is_searching() && !poll(Duration::ZERO)? {
+            editors[current_editor].search_step();
+            if editors[current_editor].refresh {
+                editors[current_editor].render(&mut screen, show_title)?;
+            }
+            continue;
+        }
+
         let event = read()?;
         match event {
             Event::Key(e) => {
                 // editors[current_editor].update(e);
-                if e.code == KeyCode::Char('B') {
+                let normal_mode = editors[current_editor].is_normal_mode();
+                if normal_mode && e.code == KeyCode::Char('B') {
 
-                    current_editor = cmp::max(current_editor - 1, 0);
+                    current_editor = current_editor.saturating_sub(1);
                     editors[current_editor].refresh = true;
                 } 
-                else if e.code == KeyCode::Char('N') {
+                else if normal_mode && e.code == KeyCode::Char('N') {
                     current_editor = cmp::min(current_editor + 1, editors.len() - 1);
                     editors[current_editor].refresh = true;
                 } 
-                else if e.code == KeyCode::Tab {
+                else if e.code == KeyCode::Char('l') && e.modifiers.contains(KeyModifiers::CONTROL) {
+                    // The terminal may have been messed up by something else
+                    screen.invalidate();
+                    editors[current_editor].render(&mut screen, show_title)?;
+                }
+                else if normal_mode && e.code == KeyCode::Char('T') {
                     show_title = !show_title;
-                    editors[current_editor].render(&mut stdout, show_title)?;
+                    editors[current_editor].render(&mut screen, show_title)?;
 
                 } else {
                     editors[current_editor].update(e);
                 }
             },
-            Event::Resize(_,_ ) => editors[current_editor].render(&mut stdout, show_title)?,
+            Event::Resize(_,_ ) => editors[current_editor].render(&mut screen, show_title)?,
             _ => {
             
             }
         }
+        if editors[current_editor].exit {
+            let unsaved_files = editors.iter().filter(|e| e.is_modified()).count();
+            if unsaved_files == 0 || editors[current_editor].force_exit {
+                break;
+            }
+            editors[current_editor].confirm_exit(unsaved_files);
+        }
         if editors[current_editor].refresh {
-            editors[current_editor].render(&mut stdout, show_title)?;
+            editors[current_editor].render(&mut screen, show_title)?;
         }
     }
 
-    stdout
-        .execute(Clear(ClearType::All))?
-        .execute(cursor::Show)?
-        .execute(cursor::MoveTo(0, 0))?;
-    let _ = disable_raw_mode();
-
     Ok(())
 }
 